@@ -1,9 +1,7 @@
-use diagv::{generators, Graph};
+use diagv::{generators, Graph, ParseError};
 use leptos::prelude::RwSignal;
 use leptos::prelude::*;
 use leptos::{component, view, IntoView};
-use std::fmt::Display;
-use std::hash::Hash;
 use thaw::*;
 
 static DIAGV_INPUT: &str = r#"digraph {
@@ -15,17 +13,6 @@ static DIAGV_INPUT: &str = r#"digraph {
 }
 "#;
 
-fn fmt_dot<N: Clone + Display + Eq + Hash>(input: Graph<N>) -> String {
-    let mut output = String::new();
-    output.push_str("digraph {\n");
-    for (tail, heads) in input.heads().into_iter() {
-        for head in heads.into_iter() {
-            output.push_str(&format!("  \"{tail}\" -> \"{head}\";\n"));
-        }
-    }
-    output.push_str("}\n");
-    output
-}
 #[component]
 pub fn InputPage(
     topology: RwSignal<String>,
@@ -34,12 +21,44 @@ pub fn InputPage(
     let on_select = move |key: String| {
         let dot = match key.as_str() {
             "diagv" => DIAGV_INPUT.to_string(),
-            "Cycle(9)" => fmt_dot(generators::cycle(9)),
-            "Sonic(3)" => fmt_dot(generators::sonic(3)),
+            "Cycle(9)" => generators::cycle(9).to_dot(),
+            "Sonic(3)" => generators::sonic(3).to_dot(),
             v => unreachable!("{v}"),
         };
         topology.set(dot);
     };
+    let error = RwSignal::new(None::<ParseError>);
+    let render = move |_| match Graph::parse_dot(&topology.get()) {
+        Ok(_) => {
+            error.set(None);
+            on_render();
+        }
+        Err(e) => error.set(Some(e)),
+    };
+    let underlined = move || {
+        error.get().map(|e| {
+            let span = e.span();
+            let chars: Vec<char> = span.snippet.chars().collect();
+            let start = (span.column - 1).min(chars.len());
+            let end = (start + span.width).min(chars.len());
+            let before: String = chars[..start].iter().collect();
+            let marked: String = match chars[start..end].iter().collect::<String>() {
+                marked if marked.is_empty() => String::from(" "),
+                marked => marked,
+            };
+            let after: String = chars[end..].iter().collect();
+            view! {
+                <div class="parse-error">
+                    <div>{e.to_string()}</div>
+                    <pre>
+                        {format!("{} | {before}", span.line)}
+                        <span class="parse-error-underline">{marked}</span>
+                        {after}
+                    </pre>
+                </div>
+            }
+        })
+    };
     view! {
         <Flex style="height: 100dvh; gap: 0;" vertical=true>
             <Textarea
@@ -48,6 +67,7 @@ pub fn InputPage(
                 rules=vec![TextareaRule::required(true.into())]
                 value=topology
             />
+            {underlined}
             <Flex justify=FlexJustify::End style="padding: 1em">
                 <Menu on_select>
                     <MenuTrigger slot>
@@ -59,7 +79,7 @@ pub fn InputPage(
                 </Menu>
                 <Button
                     appearance=ButtonAppearance::Primary
-                    on_click=move |_| on_render()
+                    on_click=render
                     icon=icondata_mdi::MdiChevronRight
                 >
                     Render
@@ -1,37 +1,109 @@
 use itertools::Itertools;
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fmt::Debug;
 use std::hash::Hash;
+use std::iter::FromIterator;
+
+/// Position of a node in the order that the nodes were added to a [`Graph`].
+///
+/// Ids stay valid until a node is removed, which shifts the ids of every later node down by one.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct NodeId(usize);
+
+impl NodeId {
+    pub fn index(self) -> usize {
+        self.0
+    }
+}
+
+/// Named values attached to a node or an edge, such as `label`.
+pub type Attributes = BTreeMap<String, String>;
+
+static NO_ATTRIBUTES: Attributes = BTreeMap::new();
+
+/// A named group of nodes, such as a DOT subgraph or cluster.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Subgraph {
+    pub name: String,
+    /// Position in [`Graph::subgraphs`] of the subgraph that encloses this one, if any.
+    pub parent: Option<usize>,
+    /// Members in the order that they were first mentioned, including those of nested subgraphs.
+    pub nodes: Vec<NodeId>,
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Attributes::is_empty")
+    )]
+    pub attrs: Attributes,
+}
 
 pub struct Graph<T> {
     nodes: Vec<T>,
+    index: HashMap<T, usize>,
     heads: Vec<Vec<usize>>,
+    tails: Vec<Vec<usize>>,
+    attrs: Attributes,
+    node_attrs: Vec<Attributes>,
+    edge_attrs: HashMap<(usize, usize), Attributes>,
+    subgraphs: Vec<Subgraph>,
+    directed: bool,
+}
+
+impl<T> Default for Graph<T> {
+    fn default() -> Self {
+        Self {
+            nodes: Vec::new(),
+            index: HashMap::new(),
+            heads: Vec::new(),
+            tails: Vec::new(),
+            attrs: Attributes::new(),
+            node_attrs: Vec::new(),
+            edge_attrs: HashMap::new(),
+            subgraphs: Vec::new(),
+            directed: true,
+        }
+    }
 }
 
 impl<T> Graph<T>
 where
-    T: Debug + Eq + Hash,
+    T: Clone + Debug + Eq + Hash,
 {
     pub(crate) fn from_normalized(
         nodes: HashMap<T, usize>,
         mut heads: HashMap<usize, HashSet<usize>>,
     ) -> Self {
+        let heads: Vec<Vec<usize>> = (0..nodes.len())
+            .map(|i| {
+                heads
+                    .remove(&i)
+                    .unwrap_or_default()
+                    .into_iter()
+                    .sorted()
+                    .collect()
+            })
+            .collect();
+        let mut tails = vec![Vec::new(); nodes.len()];
+        for (t, hs) in heads.iter().enumerate() {
+            for h in hs {
+                tails[*h].push(t);
+            }
+        }
         Self {
-            heads: (0..nodes.len())
-                .map(|i| {
-                    heads
-                        .remove(&i)
-                        .unwrap_or_default()
-                        .into_iter()
-                        .sorted()
-                        .collect()
-                })
-                .collect(),
+            heads,
+            tails,
+            attrs: Attributes::new(),
+            node_attrs: vec![Attributes::new(); nodes.len()],
+            edge_attrs: HashMap::new(),
+            subgraphs: Vec::new(),
+            directed: true,
             nodes: nodes
-                .into_iter()
-                .sorted_by_key(|(_, i)| *i)
-                .map(|(n, _)| n)
+                .iter()
+                .sorted_by_key(|(_, i)| **i)
+                .map(|(n, _)| n.clone())
                 .collect(),
+            index: nodes,
         }
     }
 
@@ -95,21 +167,247 @@ where
 
 impl<T> Graph<T>
 where
-    T: Eq + Hash,
+    T: Clone + Eq + Hash,
 {
-    pub fn heads(&self) -> HashMap<&T, HashSet<&T>> {
-        let mut heads = HashMap::new();
-        for (t, hs) in self.heads.iter().enumerate() {
-            let t = &self.nodes[t];
-            for h in hs.iter() {
-                let h = &self.nodes[*h];
-                heads.entry(t).or_insert_with(HashSet::new).insert(h);
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn insert(&mut self, node: T) -> usize {
+        if let Some(i) = self.index.get(&node) {
+            return *i;
+        }
+        let i = self.nodes.len();
+        self.index.insert(node.clone(), i);
+        self.nodes.push(node);
+        self.heads.push(Vec::new());
+        self.tails.push(Vec::new());
+        self.node_attrs.push(Attributes::new());
+        i
+    }
+
+    /// Add `node` after all existing nodes.
+    ///
+    /// Return `false` if the node was already present.
+    pub fn add_node(&mut self, node: T) -> bool {
+        let n = self.nodes.len();
+        self.insert(node) == n
+    }
+
+    /// Add an edge from `tail` to `head`, adding any missing node after all existing nodes.
+    ///
+    /// Return `false` if the edge was already present.
+    pub fn add_edge(&mut self, tail: T, head: T) -> bool {
+        let t = self.insert(tail);
+        let h = self.insert(head);
+        match self.heads[t].binary_search(&h) {
+            Ok(_) => false,
+            Err(pos) => {
+                self.heads[t].insert(pos, h);
+                let pos = self.tails[h].binary_search(&t).unwrap_err();
+                self.tails[h].insert(pos, t);
+                true
+            }
+        }
+    }
+
+    /// Remove `node` and every edge to or from it, keeping the order of the remaining nodes.
+    ///
+    /// Every [`NodeId`] after the removed node then refers to the node after the one it did, so
+    /// look ids up again with [`Graph::id_of`]. Return `false` if the node was not present.
+    pub fn remove_node(&mut self, node: &T) -> bool {
+        let i = match self.index.remove(node) {
+            Some(i) => i,
+            None => return false,
+        };
+        self.nodes.remove(i);
+        self.heads.remove(i);
+        self.tails.remove(i);
+        self.node_attrs.remove(i);
+        let shifted = |n: usize| if i < n { n - 1 } else { n };
+        self.edge_attrs = std::mem::take(&mut self.edge_attrs)
+            .into_iter()
+            .filter(|((t, h), _)| *t != i && *h != i)
+            .map(|((t, h), attrs)| ((shifted(t), shifted(h)), attrs))
+            .collect();
+        for subgraph in self.subgraphs.iter_mut() {
+            subgraph.nodes.retain(|n| n.0 != i);
+            for n in subgraph.nodes.iter_mut() {
+                n.0 = shifted(n.0);
+            }
+        }
+        for ns in self.heads.iter_mut().chain(self.tails.iter_mut()) {
+            ns.retain(|n| *n != i);
+            for n in ns.iter_mut() {
+                if i < *n {
+                    *n -= 1;
+                }
+            }
+        }
+        for j in self.index.values_mut() {
+            if i < *j {
+                *j -= 1;
+            }
+        }
+        true
+    }
+
+    /// Remove the edge from `tail` to `head`, keeping both nodes.
+    ///
+    /// Return `false` if the edge was not present.
+    pub fn remove_edge(&mut self, tail: &T, head: &T) -> bool {
+        let (t, h) = match (self.index.get(tail), self.index.get(head)) {
+            (Some(t), Some(h)) => (*t, *h),
+            _ => return false,
+        };
+        match self.heads[t].binary_search(&h) {
+            Ok(pos) => {
+                self.heads[t].remove(pos);
+                let pos = self.tails[h].binary_search(&t).unwrap();
+                self.tails[h].remove(pos);
+                self.edge_attrs.remove(&(t, h));
+                true
             }
+            Err(_) => false,
         }
-        for n in self.nodes.iter() {
-            heads.entry(n).or_default();
+    }
+}
+
+impl<T> FromIterator<(T, T)> for Graph<T>
+where
+    T: Clone + Eq + Hash,
+{
+    fn from_iter<I: IntoIterator<Item = (T, T)>>(iter: I) -> Self {
+        let mut graph = Self::new();
+        graph.extend(iter);
+        graph
+    }
+}
+
+impl<T> Extend<(T, T)> for Graph<T>
+where
+    T: Clone + Eq + Hash,
+{
+    fn extend<I: IntoIterator<Item = (T, T)>>(&mut self, iter: I) {
+        for (t, h) in iter {
+            self.add_edge(t, h);
         }
-        heads
+    }
+}
+
+impl<T> Graph<T> {
+    /// Return the number of nodes.
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    pub fn node(&self, id: NodeId) -> &T {
+        &self.nodes[id.0]
+    }
+
+    /// Return the id of every node in the order that they were added.
+    pub fn node_ids(&self) -> impl Iterator<Item = NodeId> {
+        (0..self.nodes.len()).map(NodeId)
+    }
+
+    /// Return the heads of the edges from `id` in index order.
+    pub fn successors(&self, id: NodeId) -> impl Iterator<Item = NodeId> + '_ {
+        self.heads[id.0].iter().copied().map(NodeId)
+    }
+
+    /// Return the tails of the edges to `id` in index order.
+    pub fn predecessors(&self, id: NodeId) -> impl Iterator<Item = NodeId> + '_ {
+        self.tails[id.0].iter().copied().map(NodeId)
+    }
+
+    pub fn has_edge(&self, tail: NodeId, head: NodeId) -> bool {
+        self.heads[tail.0].binary_search(&head.0).is_ok()
+    }
+
+    /// Return the attributes of the graph itself.
+    pub fn attrs(&self) -> &Attributes {
+        &self.attrs
+    }
+
+    pub fn attrs_mut(&mut self) -> &mut Attributes {
+        &mut self.attrs
+    }
+
+    pub fn node_attrs(&self, id: NodeId) -> &Attributes {
+        &self.node_attrs[id.0]
+    }
+
+    pub fn node_attrs_mut(&mut self, id: NodeId) -> &mut Attributes {
+        &mut self.node_attrs[id.0]
+    }
+
+    /// Return the attributes of the edge from `tail` to `head`, or `None` if there is no such edge.
+    pub fn edge_attrs(&self, tail: NodeId, head: NodeId) -> Option<&Attributes> {
+        if !self.has_edge(tail, head) {
+            return None;
+        }
+        Some(
+            self.edge_attrs
+                .get(&(tail.0, head.0))
+                .unwrap_or(&NO_ATTRIBUTES),
+        )
+    }
+
+    pub fn edge_attrs_mut(&mut self, tail: NodeId, head: NodeId) -> Option<&mut Attributes> {
+        if !self.has_edge(tail, head) {
+            return None;
+        }
+        Some(self.edge_attrs.entry((tail.0, head.0)).or_default())
+    }
+
+    /// Return whether the edges have a direction, which is the default.
+    ///
+    /// Edges of an undirected graph are still kept in the direction that they were added in, which
+    /// decides how they are laid out.
+    pub fn is_directed(&self) -> bool {
+        self.directed
+    }
+
+    pub fn set_directed(&mut self, directed: bool) {
+        self.directed = directed;
+    }
+
+    /// Return the subgraphs in the order that they were added, with parents before children.
+    pub fn subgraphs(&self) -> &[Subgraph] {
+        &self.subgraphs
+    }
+
+    pub fn subgraphs_mut(&mut self) -> &mut Vec<Subgraph> {
+        &mut self.subgraphs
+    }
+
+    /// Return every edge as a `(tail, head)` pair ordered by tail and then by head.
+    pub fn edges(&self) -> impl Iterator<Item = (NodeId, NodeId)> + '_ {
+        self.heads
+            .iter()
+            .enumerate()
+            .flat_map(|(t, hs)| hs.iter().map(move |h| (NodeId(t), NodeId(*h))))
+    }
+}
+
+impl<T> Graph<T>
+where
+    T: Eq + Hash,
+{
+    pub fn id_of(&self, node: &T) -> Option<NodeId> {
+        self.index.get(node).copied().map(NodeId)
+    }
+
+    pub fn heads(&self) -> HashMap<&T, HashSet<&T>> {
+        self.nodes
+            .iter()
+            .zip(self.heads.iter())
+            .map(|(t, hs)| (t, hs.iter().map(|h| &self.nodes[*h]).collect()))
+            .collect()
     }
 
     pub fn nodes(&self) -> Vec<&T> {
@@ -117,24 +415,18 @@ where
     }
 
     pub fn tails(&self) -> HashMap<&T, HashSet<&T>> {
-        let mut tails = HashMap::new();
-        for (t, hs) in self.heads.iter().enumerate() {
-            let t = &self.nodes[t];
-            for h in hs.iter() {
-                let h = &self.nodes[*h];
-                tails.entry(h).or_insert_with(HashSet::new).insert(t);
-            }
-        }
-        for n in self.nodes.iter() {
-            tails.entry(n).or_default();
-        }
-        tails
+        self.nodes
+            .iter()
+            .zip(self.tails.iter())
+            .map(|(h, ts)| (h, ts.iter().map(|t| &self.nodes[*t]).collect()))
+            .collect()
     }
 }
 
 #[cfg(test)]
 mod tests {
     use crate::generators;
+    use crate::{Attributes, Graph, NodeId, Subgraph};
     use std::collections::HashSet;
 
     #[test]
@@ -168,4 +460,111 @@ mod tests {
         let graph = generators::diagv();
         assert_eq!(5, graph.tails().into_keys().collect::<HashSet<_>>().len());
     }
+
+    #[test]
+    fn builder_keeps_insertion_order() {
+        let mut graph: Graph<char> = vec![('b', 'a'), ('c', 'a')].into_iter().collect();
+        assert!(graph.add_node('d'));
+        assert!(!graph.add_node('a'));
+        assert!(!graph.add_edge('b', 'a'));
+        assert_eq!(graph.nodes, vec!['b', 'a', 'c', 'd']);
+        assert_eq!(graph.heads, vec![vec![1], vec![], vec![1], vec![]]);
+    }
+
+    #[test]
+    fn remove_node_reindexes_remaining_nodes() {
+        let mut graph = generators::diagv();
+        assert!(graph.remove_node(&'i'));
+        assert!(!graph.remove_node(&'i'));
+        assert_eq!(graph.nodes, vec!['d', 'a', 'g', 'v']);
+        assert_eq!(graph.heads, vec![vec![1], vec![3], vec![3], vec![]]);
+        assert!(graph.add_edge('g', 'd'));
+        assert_eq!(graph.heads[2], vec![0, 3]);
+    }
+
+    #[test]
+    fn remove_node_shifts_later_ids() {
+        let mut graph = generators::diagv();
+        let (d, a) = (graph.id_of(&'d').unwrap(), graph.id_of(&'a').unwrap());
+        graph.remove_node(&'i');
+        assert_eq!(graph.node(d), &'d');
+        assert_eq!(graph.node(a), &'g');
+        assert_eq!(graph.id_of(&'a').unwrap().index(), a.index() - 1);
+    }
+
+    #[test]
+    fn remove_edge_keeps_nodes() {
+        let mut graph = generators::diagv();
+        assert!(graph.remove_edge(&'d', &'a'));
+        assert!(!graph.remove_edge(&'d', &'a'));
+        assert_eq!(graph.nodes.len(), 5);
+        assert!(graph.heads()[&'d'].is_empty());
+    }
+
+    #[test]
+    fn adjacency_is_in_index_order() {
+        let graph = generators::diagv();
+        let a = graph.id_of(&'a').unwrap();
+        assert_eq!(*graph.node(a), 'a');
+        let names = |ids: Vec<NodeId>| ids.into_iter().map(|n| *graph.node(n)).collect::<String>();
+        assert_eq!(names(graph.predecessors(a).collect()), "di");
+        assert_eq!(names(graph.successors(a).collect()), "v");
+        let edges: Vec<_> = graph
+            .edges()
+            .map(|(t, h)| format!("{}{}", graph.node(t), graph.node(h)))
+            .collect();
+        assert_eq!(edges, vec!["da", "ia", "ig", "av", "gv"]);
+    }
+
+    #[test]
+    fn reverse_adjacency_follows_mutations() {
+        let mut graph = generators::diagv();
+        graph.add_edge('v', 'd');
+        graph.remove_edge(&'i', &'a');
+        graph.remove_node(&'g');
+        let v = graph.id_of(&'v').unwrap();
+        let d = graph.id_of(&'d').unwrap();
+        assert_eq!(graph.predecessors(d).collect::<Vec<_>>(), vec![v]);
+        assert_eq!(graph.predecessors(v).count(), 1);
+        assert_eq!(graph.id_of(&'g'), None);
+    }
+
+    #[test]
+    fn attributes_follow_mutations() {
+        let mut graph = generators::diagv();
+        let id = |graph: &Graph<char>, node| graph.id_of(&node).unwrap();
+        let (a, v) = (id(&graph, 'a'), id(&graph, 'v'));
+        graph.node_attrs_mut(v).insert("label".into(), "V".into());
+        graph
+            .edge_attrs_mut(a, v)
+            .unwrap()
+            .insert("style".into(), "dashed".into());
+        assert!(graph.edge_attrs_mut(v, a).is_none());
+        graph.remove_node(&'i');
+        let (a, v) = (id(&graph, 'a'), id(&graph, 'v'));
+        assert_eq!(graph.node_attrs(v)["label"], "V");
+        assert_eq!(graph.edge_attrs(a, v).unwrap()["style"], "dashed");
+        graph.remove_edge(&'a', &'v');
+        graph.add_edge('a', 'v');
+        assert!(graph.edge_attrs(a, v).unwrap().is_empty());
+    }
+
+    #[test]
+    fn subgraphs_follow_mutations() {
+        let mut graph = generators::diagv();
+        let nodes = graph.node_ids().collect();
+        graph.subgraphs_mut().push(Subgraph {
+            name: String::from("all"),
+            parent: None,
+            nodes,
+            attrs: Attributes::new(),
+        });
+        graph.remove_node(&'a');
+        let names: Vec<char> = graph.subgraphs()[0]
+            .nodes
+            .iter()
+            .map(|id| *graph.node(*id))
+            .collect();
+        assert_eq!(names, vec!['d', 'i', 'g', 'v']);
+    }
 }
@@ -0,0 +1,574 @@
+use crate::error::{ParseError, Span};
+use std::fmt;
+use std::ops::Range;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Keyword {
+    Strict,
+    Graph,
+    Digraph,
+    Node,
+    Edge,
+    Subgraph,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    /// An identifier or a numeral.
+    Id(String),
+    /// The text of a double quoted string, without the quotes and escapes.
+    Quoted(String),
+    /// The text between the outermost angle brackets of an HTML string.
+    Html(String),
+    Keyword(Keyword),
+    /// `->` or `--`.
+    EdgeOp(&'static str),
+    Punct(char),
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Token::Id(id) => write!(f, "'{id}'"),
+            Token::Quoted(id) => write!(f, "'\"{id}\"'"),
+            Token::Html(id) => write!(f, "'<{id}>'"),
+            Token::Keyword(keyword) => write!(f, "'{}'", format!("{keyword:?}").to_lowercase()),
+            Token::EdgeOp(op) => write!(f, "'{op}'"),
+            Token::Punct(c) => write!(f, "'{c}'"),
+        }
+    }
+}
+
+fn keyword(word: &str) -> Option<Keyword> {
+    match word.to_lowercase().as_str() {
+        "strict" => Some(Keyword::Strict),
+        "graph" => Some(Keyword::Graph),
+        "digraph" => Some(Keyword::Digraph),
+        "node" => Some(Keyword::Node),
+        "edge" => Some(Keyword::Edge),
+        "subgraph" => Some(Keyword::Subgraph),
+        _ => None,
+    }
+}
+
+fn is_id_start(c: char) -> bool {
+    c.is_ascii_alphabetic() || c == '_' || !c.is_ascii()
+}
+
+/// Return the tokens of `s` with the bytes that each spans.
+fn tokens(s: &str) -> Result<Vec<(Token, Range<usize>)>, ParseError> {
+    let chars: Vec<(usize, char)> = s.char_indices().collect();
+    let at = |i: usize| chars.get(i).map(|(_, c)| *c);
+    let offset = |i: usize| chars.get(i).map_or(s.len(), |(o, _)| *o);
+    let unterminated = |what, i: usize| ParseError::Unterminated {
+        what,
+        span: Span::new(s, offset(i), offset(i + 1)),
+    };
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while let Some(c) = at(i) {
+        let start = i;
+        match c {
+            c if c.is_whitespace() => i += 1,
+            // Lines that start with `#` are output of the C preprocessor.
+            '#' if i == 0 || at(i - 1) == Some('\n') => {
+                while !matches!(at(i), None | Some('\n')) {
+                    i += 1;
+                }
+            }
+            '/' if at(i + 1) == Some('/') => {
+                while !matches!(at(i), None | Some('\n')) {
+                    i += 1;
+                }
+            }
+            '/' if at(i + 1) == Some('*') => {
+                i += 2;
+                while (at(i), at(i + 1)) != (Some('*'), Some('/')) {
+                    if at(i).is_none() {
+                        return Err(unterminated("comment", start));
+                    }
+                    i += 1;
+                }
+                i += 2;
+            }
+            '"' => {
+                let mut text = String::new();
+                i += 1;
+                loop {
+                    match (at(i), at(i + 1)) {
+                        (None, _) => return Err(unterminated("string", start)),
+                        (Some('"'), _) => break,
+                        (Some('\\'), Some('"')) => {
+                            text.push('"');
+                            i += 1;
+                        }
+                        (Some('\\'), Some('\\')) => {
+                            text.push_str("\\\\");
+                            i += 1;
+                        }
+                        (Some('\\'), Some('\n')) => i += 1,
+                        (Some('\\'), Some('\r')) if at(i + 2) == Some('\n') => i += 2,
+                        (Some(c), _) => text.push(c),
+                    }
+                    i += 1;
+                }
+                i += 1;
+                tokens.push((Token::Quoted(text), offset(start)..offset(i)));
+            }
+            '<' => {
+                let mut text = String::new();
+                let mut depth = 1;
+                i += 1;
+                loop {
+                    match at(i) {
+                        None => return Err(unterminated("HTML string", start)),
+                        Some('>') if depth == 1 => break,
+                        Some(c) => {
+                            match c {
+                                '<' => depth += 1,
+                                '>' => depth -= 1,
+                                _ => {}
+                            }
+                            text.push(c);
+                        }
+                    }
+                    i += 1;
+                }
+                i += 1;
+                tokens.push((Token::Html(text), offset(start)..offset(i)));
+            }
+            '-' if matches!(at(i + 1), Some('>') | Some('-')) => {
+                let op = if at(i + 1) == Some('>') { "->" } else { "--" };
+                i += 2;
+                tokens.push((Token::EdgeOp(op), offset(start)..offset(i)));
+            }
+            '-' | '.' | '0'..='9' => {
+                if c == '-' {
+                    i += 1;
+                }
+                let digits = |i: &mut usize| {
+                    let from = *i;
+                    while at(*i).is_some_and(|c| c.is_ascii_digit()) {
+                        *i += 1;
+                    }
+                    *i > from
+                };
+                let whole = digits(&mut i);
+                let fraction = at(i) == Some('.') && {
+                    i += 1;
+                    digits(&mut i)
+                };
+                let range = offset(start)..offset(i);
+                if !whole && !fraction {
+                    return Err(ParseError::Expected {
+                        expected: String::from("a numeral"),
+                        found: Some(format!("'{}'", &s[range.clone()])),
+                        span: Span::new(s, range.start, range.end),
+                    });
+                }
+                tokens.push((Token::Id(s[range.clone()].to_string()), range));
+            }
+            c if is_id_start(c) => {
+                while at(i).is_some_and(|c| is_id_start(c) || c.is_ascii_digit()) {
+                    i += 1;
+                }
+                let range = offset(start)..offset(i);
+                match keyword(&s[range.clone()]) {
+                    Some(keyword) => tokens.push((Token::Keyword(keyword), range)),
+                    None => tokens.push((Token::Id(s[range.clone()].to_string()), range)),
+                }
+            }
+            '{' | '}' | '[' | ']' | '=' | ';' | ',' | ':' | '+' => {
+                i += 1;
+                tokens.push((Token::Punct(c), offset(start)..offset(i)));
+            }
+            found => {
+                return Err(ParseError::UnexpectedChar {
+                    found,
+                    span: Span::new(s, offset(i), offset(i + 1)),
+                })
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+/// A list of attributes, by name and value.
+pub(crate) type AttrList = Vec<(String, String)>;
+
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct NodeRef {
+    pub(crate) id: String,
+    /// The port and compass point, in the order written.
+    pub(crate) port: Vec<String>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct Subgraph {
+    pub(crate) name: Option<String>,
+    pub(crate) stmts: Vec<Stmt>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum Endpoint {
+    Node(NodeRef),
+    Subgraph(Subgraph),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum AttrKind {
+    Graph,
+    Node,
+    Edge,
+}
+
+/// A statement, with every ID as the logical name that it stands for.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum Stmt {
+    Node(NodeRef, AttrList),
+    /// Two or more endpoints joined by edge operators.
+    Edge(Vec<Endpoint>, AttrList),
+    Attr(AttrKind, AttrList),
+    /// An `ID = ID` statement, which sets an attribute of the enclosing graph.
+    Assign(String, String),
+    Subgraph(Subgraph),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct DotGraph {
+    pub(crate) directed: bool,
+    pub(crate) name: Option<String>,
+    pub(crate) stmts: Vec<Stmt>,
+}
+
+struct Parser<'a> {
+    source: &'a str,
+    tokens: Vec<(Token, Range<usize>)>,
+    next: usize,
+    /// Whether the graph being parsed is a digraph, whose edges use `->` rather than `--`.
+    directed: bool,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.next).map(|(t, _)| t)
+    }
+
+    fn bump(&mut self) -> Option<Token> {
+        let token = self.peek().cloned();
+        self.next += 1;
+        token
+    }
+
+    fn eat(&mut self, token: &Token) -> bool {
+        if self.peek() == Some(token) {
+            self.next += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Return an error for the next token, or for the end just after the last one.
+    fn unexpected<T>(&self, expected: &str) -> Result<T, ParseError> {
+        let (found, range) = match self.tokens.get(self.next) {
+            Some((token, range)) => (token.to_string(), range.clone()),
+            None => {
+                let end = self.tokens.last().map_or(0, |(_, range)| range.end);
+                (String::from("the end"), end..end)
+            }
+        };
+        Err(ParseError::Expected {
+            expected: expected.to_string(),
+            found: Some(found),
+            span: Span::new(self.source, range.start, range.end),
+        })
+    }
+
+    fn expect(&mut self, c: char) -> Result<(), ParseError> {
+        if !self.eat(&Token::Punct(c)) {
+            return self.unexpected(&format!("'{c}'"));
+        }
+        Ok(())
+    }
+
+    fn at_id(&self) -> bool {
+        matches!(
+            self.peek(),
+            Some(Token::Id(_) | Token::Quoted(_) | Token::Html(_))
+        )
+    }
+
+    fn at_subgraph(&self) -> bool {
+        matches!(
+            self.peek(),
+            Some(Token::Keyword(Keyword::Subgraph) | Token::Punct('{'))
+        )
+    }
+
+    /// Parse an ID, joining double quoted strings concatenated with `+`.
+    fn id(&mut self) -> Result<String, ParseError> {
+        match self.bump() {
+            Some(Token::Id(name) | Token::Html(name)) => Ok(name),
+            Some(Token::Quoted(mut name)) => {
+                while self.eat(&Token::Punct('+')) {
+                    match self.bump() {
+                        Some(Token::Quoted(more)) => name.push_str(&more),
+                        _ => {
+                            self.next -= 1;
+                            return self.unexpected("a double quoted string after '+'");
+                        }
+                    }
+                }
+                Ok(name)
+            }
+            _ => {
+                self.next -= 1;
+                self.unexpected("an ID")
+            }
+        }
+    }
+
+    fn attr_list(&mut self) -> Result<AttrList, ParseError> {
+        let mut attrs = Vec::new();
+        while self.eat(&Token::Punct('[')) {
+            while !self.eat(&Token::Punct(']')) {
+                let name = self.id()?;
+                self.expect('=')?;
+                attrs.push((name, self.id()?));
+                if !self.eat(&Token::Punct(';')) {
+                    self.eat(&Token::Punct(','));
+                }
+            }
+        }
+        Ok(attrs)
+    }
+
+    /// Parse the port, if any, of the node with `id`.
+    fn node_ref(&mut self, id: String) -> Result<NodeRef, ParseError> {
+        let mut port = Vec::new();
+        while port.len() < 2 && self.eat(&Token::Punct(':')) {
+            port.push(self.id()?);
+        }
+        Ok(NodeRef { id, port })
+    }
+
+    fn subgraph(&mut self) -> Result<Subgraph, ParseError> {
+        let mut name = None;
+        if self.eat(&Token::Keyword(Keyword::Subgraph)) && self.at_id() {
+            name = Some(self.id()?);
+        }
+        self.expect('{')?;
+        let stmts = self.stmts()?;
+        self.expect('}')?;
+        Ok(Subgraph { name, stmts })
+    }
+
+    fn endpoint(&mut self) -> Result<Endpoint, ParseError> {
+        if self.at_subgraph() {
+            Ok(Endpoint::Subgraph(self.subgraph()?))
+        } else {
+            let id = self.id()?;
+            Ok(Endpoint::Node(self.node_ref(id)?))
+        }
+    }
+
+    fn stmt(&mut self) -> Result<Stmt, ParseError> {
+        let kind = match self.peek() {
+            Some(Token::Keyword(Keyword::Graph)) => Some(AttrKind::Graph),
+            Some(Token::Keyword(Keyword::Node)) => Some(AttrKind::Node),
+            Some(Token::Keyword(Keyword::Edge)) => Some(AttrKind::Edge),
+            _ => None,
+        };
+        if let Some(kind) = kind {
+            self.next += 1;
+            if self.peek() != Some(&Token::Punct('[')) {
+                return self.unexpected("'['");
+            }
+            return Ok(Stmt::Attr(kind, self.attr_list()?));
+        }
+
+        let first = if self.at_subgraph() {
+            self.endpoint()?
+        } else if self.at_id() {
+            let id = self.id()?;
+            if self.eat(&Token::Punct('=')) {
+                return Ok(Stmt::Assign(id, self.id()?));
+            }
+            Endpoint::Node(self.node_ref(id)?)
+        } else {
+            return self.unexpected("a statement");
+        };
+        let mut endpoints = vec![first];
+        let op = if self.directed { "->" } else { "--" };
+        while let Some(Token::EdgeOp(found)) = self.peek() {
+            if *found != op {
+                return self.unexpected(&format!("'{op}'"));
+            }
+            self.next += 1;
+            endpoints.push(self.endpoint()?);
+        }
+        if endpoints.len() > 1 {
+            return Ok(Stmt::Edge(endpoints, self.attr_list()?));
+        }
+        match endpoints.pop().unwrap() {
+            Endpoint::Node(node) => Ok(Stmt::Node(node, self.attr_list()?)),
+            Endpoint::Subgraph(subgraph) => Ok(Stmt::Subgraph(subgraph)),
+        }
+    }
+
+    fn stmts(&mut self) -> Result<Vec<Stmt>, ParseError> {
+        let mut stmts = Vec::new();
+        while self.peek().is_some() && self.peek() != Some(&Token::Punct('}')) {
+            stmts.push(self.stmt()?);
+            self.eat(&Token::Punct(';'));
+        }
+        Ok(stmts)
+    }
+
+    fn graph(&mut self) -> Result<DotGraph, ParseError> {
+        // `strict` changes nothing, since a `Graph` never has two edges between the same nodes.
+        self.eat(&Token::Keyword(Keyword::Strict));
+        let directed = match self.peek() {
+            Some(Token::Keyword(Keyword::Graph)) => false,
+            Some(Token::Keyword(Keyword::Digraph)) => true,
+            _ => return self.unexpected("'graph' or 'digraph'"),
+        };
+        self.next += 1;
+        self.directed = directed;
+        let name = if self.at_id() { Some(self.id()?) } else { None };
+        self.expect('{')?;
+        let stmts = self.stmts()?;
+        self.expect('}')?;
+        Ok(DotGraph {
+            directed,
+            name,
+            stmts,
+        })
+    }
+}
+
+fn parser(s: &str) -> Result<Parser<'_>, ParseError> {
+    Ok(Parser {
+        source: s,
+        tokens: tokens(s)?,
+        next: 0,
+        directed: true,
+    })
+}
+
+/// Parse a document in the DOT language that holds one graph.
+pub(crate) fn parse(s: &str) -> Result<DotGraph, ParseError> {
+    let mut parser = parser(s)?;
+    let graph = parser.graph()?;
+    if parser.peek().is_some() {
+        return parser.unexpected("the end");
+    }
+    Ok(graph)
+}
+
+/// Parse every graph in a document in the DOT language.
+pub(crate) fn parse_all(s: &str) -> Result<Vec<DotGraph>, ParseError> {
+    let mut parser = parser(s)?;
+    let mut graphs = Vec::new();
+    while parser.peek().is_some() {
+        graphs.push(parser.graph()?);
+    }
+    Ok(graphs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse, Endpoint, Stmt};
+
+    fn names(s: &str) -> Vec<String> {
+        let graph = parse(s).unwrap();
+        let mut names = Vec::new();
+        for stmt in graph.stmts {
+            match stmt {
+                Stmt::Node(node, _) => names.push(node.id),
+                Stmt::Edge(endpoints, _) => {
+                    for endpoint in endpoints {
+                        if let Endpoint::Node(node) = endpoint {
+                            names.push(node.id);
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+        names
+    }
+
+    #[test]
+    fn ids_are_normalized_to_their_names() {
+        let dot =
+            "digraph G {\n  a_1 -> -.5 -> 3.;\n  \"say \\\"hi\\\"\" -> \"con\" + \"cat\" +\n \
+                   \"enated\";\n  <<b>bold</b>> -> \"line\\\ncontinued\" -> \"\\n\";\n  ü -> \"a\\\\\";\n}";
+        assert_eq!(
+            names(dot),
+            vec![
+                "a_1",
+                "-.5",
+                "3.",
+                "say \"hi\"",
+                "concatenated",
+                "<b>bold</b>",
+                "linecontinued",
+                "\\n",
+                "ü",
+                "a\\\\",
+            ]
+        );
+    }
+
+    #[test]
+    fn comments_and_keywords_are_not_ids() {
+        let dot = "# 1 \"input.gv\"\nDiGraph { // a\n /* b\n */ Node [shape=box] \"node\" }";
+        assert_eq!(names(dot), vec!["node"]);
+        assert!(matches!(
+            parse(dot).unwrap().stmts[0],
+            Stmt::Attr(super::AttrKind::Node, _)
+        ));
+    }
+
+    #[test]
+    fn errors_name_the_line() {
+        let error = |dot: &str| parse(dot).err().unwrap().to_string();
+        assert_eq!(
+            error("digraph {\n  a -> ;\n}"),
+            "Expected an ID but found ';' on line 2"
+        );
+        assert_eq!(
+            error("digraph {\n  \"a\" + b\n}"),
+            "Expected a double quoted string after '+' but found 'b' on line 2"
+        );
+        assert_eq!(
+            error("digraph {\n  a\n"),
+            "Expected '}' but found the end on line 2"
+        );
+        assert_eq!(
+            error("digraph { \"a }"),
+            "Unterminated string starting on line 1"
+        );
+        assert_eq!(
+            error("graph {\n  a -- b -> c\n}"),
+            "Expected '--' but found '->' on line 2"
+        );
+        assert_eq!(
+            error("digraph {}\ndigraph {}"),
+            "Expected the end but found 'digraph' on line 2"
+        );
+    }
+
+    #[test]
+    fn errors_point_at_the_token() {
+        let error = parse("digraph {\n  a -> b -> @;\n}").err().unwrap();
+        let span = error.span();
+        assert_eq!((span.line, span.column, span.width), (2, 13, 1));
+        assert_eq!(span.snippet, "  a -> b -> @;");
+        let error = parse("digraph {\n  a [label=\"x\n\n").err().unwrap();
+        let span = error.span();
+        assert_eq!((span.line, span.column, span.width), (2, 12, 1));
+    }
+}
@@ -0,0 +1,158 @@
+use std::fmt;
+use unicode_width::UnicodeWidthChar;
+
+/// Where a [`ParseError`] is in the input.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Span {
+    /// Counted from 1.
+    pub line: usize,
+    /// Counted in characters from 1.
+    pub column: usize,
+    /// Number of characters, which may be 0 at the end of the input.
+    pub width: usize,
+    /// The whole line, without its line ending.
+    pub snippet: String,
+}
+
+impl Span {
+    /// Return the span from byte `start` to byte `end` of `s`, cut at the end of its first line.
+    pub(crate) fn new(s: &str, start: usize, end: usize) -> Self {
+        let line_start = s[..start].rfind('\n').map_or(0, |i| i + 1);
+        let line_end = s[start..].find('\n').map_or(s.len(), |i| start + i);
+        let snippet = s[line_start..line_end].trim_end_matches('\r');
+        let column = s[line_start..start].chars().count() + 1;
+        Self {
+            line: s[..start].matches('\n').count() + 1,
+            column,
+            width: s[start..end.min(line_end)].chars().count(),
+            snippet: snippet.to_string(),
+        }
+    }
+
+    /// Return the span of `part`, which must be a slice of `s`.
+    pub(crate) fn of(s: &str, part: &str) -> Self {
+        let start = part.as_ptr() as usize - s.as_ptr() as usize;
+        Self::new(s, start, start + part.len())
+    }
+}
+
+/// An error in the input of a parser, such as [`Graph::parse_dot`](crate::Graph::parse_dot).
+///
+/// Every parser of [`Graph`](crate::Graph) returns it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ParseError {
+    /// Something other than what the format allows at this point, or nothing.
+    Expected {
+        expected: String,
+        found: Option<String>,
+        span: Span,
+    },
+    /// A character that does not start any token.
+    UnexpectedChar { found: char, span: Span },
+    /// A string or comment that is still open at the end of the input.
+    Unterminated { what: &'static str, span: Span },
+    /// A node that is declared more than once.
+    Duplicate { node: String, span: Span },
+    /// A node that is used without being declared.
+    Undeclared { node: String, span: Span },
+    /// Something that the format allows but that a [`Graph`](crate::Graph) cannot hold.
+    Unsupported { what: String, span: Span },
+    /// Input that the reader of an underlying format, such as CSV or XML, rejects.
+    Malformed { message: String, span: Span },
+}
+
+impl ParseError {
+    pub fn span(&self) -> &Span {
+        match self {
+            ParseError::Expected { span, .. }
+            | ParseError::UnexpectedChar { span, .. }
+            | ParseError::Unterminated { span, .. }
+            | ParseError::Duplicate { span, .. }
+            | ParseError::Undeclared { span, .. }
+            | ParseError::Unsupported { span, .. }
+            | ParseError::Malformed { span, .. } => span,
+        }
+    }
+
+    fn message(&self) -> String {
+        match self {
+            ParseError::Expected {
+                expected,
+                found: Some(found),
+                ..
+            } => format!("Expected {expected} but found {found}"),
+            ParseError::Expected { expected, .. } => format!("Expected {expected}"),
+            ParseError::UnexpectedChar { found, .. } => format!("Unexpected {found:?}"),
+            ParseError::Unterminated { what, .. } => format!("Unterminated {what}"),
+            ParseError::Duplicate { node, .. } => format!("Node {node:?} is declared again"),
+            ParseError::Undeclared { node, .. } => format!("Undeclared node {node:?}"),
+            ParseError::Unsupported { what, .. } => format!("Unsupported {what}"),
+            ParseError::Malformed { message, .. } => message.clone(),
+        }
+    }
+
+    /// Return the error with the line that it is on and a caret under where on the line it is.
+    pub fn diagnostic(&self) -> String {
+        let span = self.span();
+        let number = span.line.to_string();
+        let gutter = " ".repeat(number.len());
+        let indent: String = span
+            .snippet
+            .chars()
+            .take(span.column - 1)
+            .map(|c| match c {
+                '\t' => String::from("\t"),
+                c => " ".repeat(c.width().unwrap_or(0)),
+            })
+            .collect();
+        let carets: usize = span
+            .snippet
+            .chars()
+            .skip(span.column - 1)
+            .take(span.width)
+            .map(|c| c.width().unwrap_or(0))
+            .sum();
+        format!(
+            "error: {}\n{gutter}--> line {}, column {}\n{gutter} |\n{number} | {}\n{gutter} | {indent}{}",
+            self.message(),
+            span.line,
+            span.column,
+            span.snippet,
+            "^".repeat(carets.max(1)),
+        )
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseError::Unterminated { span, .. } => {
+                write!(f, "{} starting on line {}", self.message(), span.line)
+            }
+            _ => write!(f, "{} on line {}", self.message(), self.span().line),
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+#[cfg(test)]
+mod tests {
+    use super::{ParseError, Span};
+
+    #[test]
+    fn diagnostic_points_at_the_span() {
+        let s = "digraph {\n\tü -> ;\n}";
+        let error = ParseError::Expected {
+            expected: String::from("an ID"),
+            found: Some(String::from("';'")),
+            span: Span::of(s, &s[17..18]),
+        };
+        assert_eq!(error.span().column, 7);
+        assert_eq!(error.to_string(), "Expected an ID but found ';' on line 2");
+        assert_eq!(
+            error.diagnostic(),
+            "error: Expected an ID but found ';'\n --> line 2, column 7\n  |\n2 | \tü -> ;\n  | \t     ^"
+        );
+    }
+}
@@ -0,0 +1,86 @@
+use crate::core::{Attributes, Graph};
+use std::fmt::{Display, Write};
+
+/// Return `id` as a double quoted DOT ID.
+pub(crate) fn quoted(id: &str) -> String {
+    format!("\"{}\"", id.replace('"', "\\\""))
+}
+
+/// Return `attrs` as a DOT attribute list preceded by a space, or nothing if there are none.
+fn attr_list(attrs: &Attributes) -> String {
+    if attrs.is_empty() {
+        return String::new();
+    }
+    let list: Vec<String> = attrs
+        .iter()
+        .map(|(name, value)| format!("{}={}", quoted(name), quoted(value)))
+        .collect();
+    format!(" [{}]", list.join(", "))
+}
+
+impl<T> Graph<T>
+where
+    T: Display,
+{
+    /// Return the graph in the DOT format, such that [`Graph::parse_dot`] gives it back.
+    ///
+    /// Every node gets its own statement, in order, before the subgraphs and the edges. Attributes
+    /// are written on the statement of whatever they belong to.
+    ///
+    /// DOT has no escape for a backslash, so names and attributes with a backslash right before a
+    /// double quote or at the end are written as they are and cannot be read back.
+    pub fn to_dot(&self) -> String {
+        let ids: Vec<String> = self
+            .node_ids()
+            .map(|id| quoted(&self.node(id).to_string()))
+            .collect();
+        let (mut dot, op) = if self.is_directed() {
+            (String::from("digraph {\n"), "->")
+        } else {
+            (String::from("graph {\n"), "--")
+        };
+        if !self.attrs().is_empty() {
+            writeln!(dot, "  graph{};", attr_list(self.attrs())).unwrap();
+        }
+        for id in self.node_ids() {
+            let attrs = attr_list(self.node_attrs(id));
+            writeln!(dot, "  {}{attrs};", ids[id.index()]).unwrap();
+        }
+        for (i, subgraph) in self.subgraphs().iter().enumerate() {
+            if subgraph.parent.is_none() {
+                self.write_subgraph(&mut dot, &ids, i, 1);
+            }
+        }
+        for (tail, head) in self.edges() {
+            let attrs = attr_list(self.edge_attrs(tail, head).unwrap());
+            writeln!(
+                dot,
+                "  {} {op} {}{attrs};",
+                ids[tail.index()],
+                ids[head.index()]
+            )
+            .unwrap();
+        }
+        dot.push_str("}\n");
+        dot
+    }
+
+    /// Write subgraph `i` with every member and every nested subgraph.
+    fn write_subgraph(&self, dot: &mut String, ids: &[String], i: usize, depth: usize) {
+        let indent = "  ".repeat(depth);
+        let subgraph = &self.subgraphs()[i];
+        writeln!(dot, "{indent}subgraph {} {{", quoted(&subgraph.name)).unwrap();
+        if !subgraph.attrs.is_empty() {
+            writeln!(dot, "{indent}  graph{};", attr_list(&subgraph.attrs)).unwrap();
+        }
+        for node in subgraph.nodes.iter() {
+            writeln!(dot, "{indent}  {};", ids[node.index()]).unwrap();
+        }
+        for (j, nested) in self.subgraphs().iter().enumerate() {
+            if nested.parent == Some(i) {
+                self.write_subgraph(dot, ids, j, depth + 1);
+            }
+        }
+        writeln!(dot, "{indent}}}").unwrap();
+    }
+}
@@ -0,0 +1,476 @@
+use crate::core::{Attributes, Graph};
+use crate::error::{ParseError, Span};
+use roxmltree::{Node, TextPos};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::{Display, Write};
+
+/// A `<key>`, which declares the name of some `<data>` and optionally its default value.
+struct Key {
+    domain: String,
+    name: String,
+    default: Option<String>,
+}
+
+struct Edge {
+    source: String,
+    target: String,
+    directed: bool,
+    attrs: Attributes,
+    span: Span,
+}
+
+/// Return `text` escaped for XML content and attribute values.
+///
+/// Tabs and line breaks are written as character references, since attribute values are
+/// normalized to spaces and carriage returns anywhere to line feeds.
+fn escaped(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => result.push_str("&amp;"),
+            '<' => result.push_str("&lt;"),
+            '>' => result.push_str("&gt;"),
+            '"' => result.push_str("&quot;"),
+            '\t' => result.push_str("&#9;"),
+            '\n' => result.push_str("&#10;"),
+            '\r' => result.push_str("&#13;"),
+            c => result.push(c),
+        }
+    }
+    result
+}
+
+/// Return the span of `node` in the document, cut at the end of the line that it starts on.
+fn span(node: Node) -> Span {
+    let range = node.range();
+    Span::new(node.document().input_text(), range.start, range.end)
+}
+
+/// Return the byte offset of `pos`, whose column is counted in characters.
+fn offset(s: &str, pos: TextPos) -> usize {
+    let line: usize = s
+        .split_inclusive('\n')
+        .take(pos.row as usize - 1)
+        .map(str::len)
+        .sum();
+    s[line..]
+        .char_indices()
+        .nth(pos.col as usize - 1)
+        .map_or(s.len(), |(i, _)| line + i)
+}
+
+fn unsupported(node: Node, what: String) -> ParseError {
+    ParseError::Unsupported {
+        what,
+        span: span(node),
+    }
+}
+
+fn elements<'a, 'input>(
+    parent: Node<'a, 'input>,
+    name: &'static str,
+) -> impl Iterator<Item = Node<'a, 'input>> {
+    parent
+        .children()
+        .filter(move |n| n.is_element() && n.tag_name().name() == name)
+}
+
+fn required<'a>(element: Node<'a, '_>, name: &str) -> Result<&'a str, ParseError> {
+    match element.attribute(name) {
+        Some(value) => Ok(value),
+        None => Err(ParseError::Expected {
+            expected: format!("{} to have a {name} attribute", element.tag_name().name()),
+            found: None,
+            span: span(element),
+        }),
+    }
+}
+
+/// Return the text of `element`, including that of any nested elements, such as yEd labels.
+fn text(element: Node) -> String {
+    if element.children().all(|n| n.is_text()) {
+        return element.text().unwrap_or_default().to_string();
+    }
+    element
+        .descendants()
+        .filter(|n| n.is_text())
+        .filter_map(|n| n.text())
+        .collect::<String>()
+        .trim()
+        .to_string()
+}
+
+fn data(element: Node, keys: &HashMap<&str, Key>, domain: &str) -> Result<Attributes, ParseError> {
+    let mut attrs: Attributes = keys
+        .values()
+        .filter(|k| k.domain == domain || k.domain == "all")
+        .filter_map(|k| Some((k.name.clone(), k.default.clone()?)))
+        .collect();
+    for data in elements(element, "data") {
+        let id = required(data, "key")?;
+        match keys.get(id) {
+            Some(key) => attrs.insert(key.name.clone(), text(data)),
+            None => {
+                return Err(ParseError::Expected {
+                    expected: String::from("a declared key"),
+                    found: Some(format!("{id:?}")),
+                    span: span(data),
+                })
+            }
+        };
+    }
+    Ok(attrs)
+}
+
+/// Collect the nodes and edges of `graph` and of any graphs nested in it, in document order.
+///
+/// Graphs without an `edgedefault` take that of the graph that they are nested in.
+fn flattened(
+    graph: Node,
+    keys: &HashMap<&str, Key>,
+    directed: bool,
+    nodes: &mut Vec<(String, Attributes, Span)>,
+    edges: &mut Vec<Edge>,
+) -> Result<(), ParseError> {
+    let directed = match graph.attribute("edgedefault") {
+        None => directed,
+        Some("directed") => true,
+        Some("undirected") => false,
+        Some(other) => return Err(unsupported(graph, format!("edgedefault {other:?}"))),
+    };
+    for child in graph.children().filter(|n| n.is_element()) {
+        match child.tag_name().name() {
+            "node" => {
+                let id = required(child, "id")?;
+                if let Some(port) = elements(child, "port").next() {
+                    return Err(unsupported(port, format!("port on node {id:?}")));
+                }
+                nodes.push((id.to_string(), data(child, keys, "node")?, span(child)));
+            }
+            "edge" => {
+                let source = required(child, "source")?;
+                let target = required(child, "target")?;
+                if child.has_attribute("sourceport") || child.has_attribute("targetport") {
+                    return Err(unsupported(
+                        child,
+                        format!("ports on the edge from {source:?} to {target:?}"),
+                    ));
+                }
+                edges.push(Edge {
+                    source: source.to_string(),
+                    target: target.to_string(),
+                    directed: match child.attribute("directed") {
+                        Some("true") => true,
+                        Some("false") => false,
+                        Some(other) => {
+                            return Err(unsupported(child, format!("directed {other:?}")))
+                        }
+                        None => directed,
+                    },
+                    attrs: data(child, keys, "edge")?,
+                    span: span(child),
+                });
+            }
+            "hyperedge" => return Err(unsupported(child, String::from("hyperedge"))),
+            "locator" => {
+                return Err(unsupported(
+                    child,
+                    String::from("graph in another document"),
+                ))
+            }
+            _ => {}
+        }
+        for nested in elements(child, "graph") {
+            flattened(nested, keys, directed, nodes, edges)?;
+        }
+    }
+    Ok(())
+}
+
+impl Graph<String> {
+    /// Parse a GraphML document that holds one graph, with nodes in document order.
+    ///
+    /// `<data>` becomes attributes named after their key. Graphs nested in nodes are flattened
+    /// into the graph. If every edge is undirected, the graph is undirected with one edge for each
+    /// pair of nodes, as with [`Graph::parse_dot`]. Otherwise undirected edges become one edge in
+    /// each direction.
+    pub fn parse_graphml(s: &str) -> Result<Self, ParseError> {
+        let document = match roxmltree::Document::parse(s) {
+            Ok(document) => document,
+            Err(error) => {
+                let start = offset(s, error.pos());
+                let message = error.to_string();
+                let suffix = format!(" at {}", error.pos());
+                return Err(ParseError::Malformed {
+                    message: message.trim_end_matches(&suffix).to_string(),
+                    span: Span::new(s, start, start),
+                });
+            }
+        };
+        let root = document.root_element();
+        if root.tag_name().name() != "graphml" {
+            return Err(ParseError::Expected {
+                expected: String::from("graphml"),
+                found: Some(root.tag_name().name().to_string()),
+                span: span(root),
+            });
+        }
+
+        let mut keys = HashMap::new();
+        for key in elements(root, "key") {
+            let id = required(key, "id")?;
+            keys.insert(
+                id,
+                Key {
+                    domain: key.attribute("for").unwrap_or("all").to_string(),
+                    name: key.attribute("attr.name").unwrap_or(id).to_string(),
+                    default: elements(key, "default").next().map(text),
+                },
+            );
+        }
+
+        let mut graphs = elements(root, "graph");
+        let graph = match (graphs.next(), graphs.next()) {
+            (Some(graph), None) => graph,
+            (_, second) => {
+                return Err(ParseError::Expected {
+                    expected: String::from("exactly one graph"),
+                    found: None,
+                    span: span(second.unwrap_or(root)),
+                })
+            }
+        };
+        let mut nodes = Vec::new();
+        let mut edges = Vec::new();
+        flattened(graph, &keys, true, &mut nodes, &mut edges)?;
+
+        let mut result = Self::new();
+        for (node, attrs, span) in nodes {
+            if !result.add_node(node.clone()) {
+                return Err(ParseError::Duplicate { node, span });
+            }
+            *result.node_attrs_mut(result.id_of(&node).unwrap()) = attrs;
+        }
+        let undirected = graph.attribute("edgedefault") == Some("undirected")
+            && edges.iter().all(|e| !e.directed);
+        for edge in edges {
+            let ends = [(&edge.source, &edge.target), (&edge.target, &edge.source)];
+            let ends = if edge.directed || undirected {
+                &ends[..1]
+            } else {
+                &ends[..]
+            };
+            for (tail, head) in ends {
+                let (t, h) = match (result.id_of(tail), result.id_of(head)) {
+                    (Some(t), Some(h)) => (t, h),
+                    (t, _) => {
+                        return Err(ParseError::Undeclared {
+                            node: if t.is_none() { tail } else { head }.to_string(),
+                            span: edge.span.clone(),
+                        })
+                    }
+                };
+                let (t, h) = if undirected && result.has_edge(h, t) {
+                    (h, t)
+                } else {
+                    result.add_edge(tail.to_string(), head.to_string());
+                    (t, h)
+                };
+                result
+                    .edge_attrs_mut(t, h)
+                    .unwrap()
+                    .extend(edge.attrs.clone());
+            }
+        }
+        result.set_directed(!undirected);
+        Ok(result)
+    }
+}
+
+impl<T> Graph<T>
+where
+    T: Display,
+{
+    /// Return the graph as a GraphML document, with every attribute as a string `<key>`.
+    pub fn to_graphml(&self) -> String {
+        let mut node_keys = BTreeMap::new();
+        for id in self.node_ids() {
+            for name in self.node_attrs(id).keys() {
+                node_keys.insert(name, 0);
+            }
+        }
+        let mut edge_keys = BTreeMap::new();
+        for (tail, head) in self.edges() {
+            for name in self.edge_attrs(tail, head).unwrap().keys() {
+                edge_keys.insert(name, 0);
+            }
+        }
+
+        let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+        xml.push_str("<graphml xmlns=\"http://graphml.graphdrawing.org/xmlns\">\n");
+        let domains = [("node", &mut node_keys), ("edge", &mut edge_keys)];
+        let mut i = 0;
+        for (domain, keys) in domains {
+            for (name, id) in keys.iter_mut() {
+                *id = i;
+                writeln!(
+                    xml,
+                    "  <key id=\"d{i}\" for=\"{domain}\" attr.name=\"{}\" attr.type=\"string\"/>",
+                    escaped(name)
+                )
+                .unwrap();
+                i += 1;
+            }
+        }
+        let write_data = |xml: &mut String, attrs: &Attributes, keys: &BTreeMap<&String, usize>| {
+            for (name, value) in attrs.iter() {
+                writeln!(
+                    xml,
+                    "      <data key=\"d{}\">{}</data>",
+                    keys[name],
+                    escaped(value)
+                )
+                .unwrap();
+            }
+        };
+
+        let edgedefault = if self.is_directed() {
+            "directed"
+        } else {
+            "undirected"
+        };
+        writeln!(xml, "  <graph edgedefault=\"{edgedefault}\">").unwrap();
+        let ids: Vec<String> = self
+            .node_ids()
+            .map(|id| escaped(&self.node(id).to_string()))
+            .collect();
+        for id in self.node_ids() {
+            let attrs = self.node_attrs(id);
+            if attrs.is_empty() {
+                writeln!(xml, "    <node id=\"{}\"/>", ids[id.index()]).unwrap();
+            } else {
+                writeln!(xml, "    <node id=\"{}\">", ids[id.index()]).unwrap();
+                write_data(&mut xml, attrs, &node_keys);
+                xml.push_str("    </node>\n");
+            }
+        }
+        for (tail, head) in self.edges() {
+            let attrs = self.edge_attrs(tail, head).unwrap();
+            let (source, target) = (&ids[tail.index()], &ids[head.index()]);
+            if attrs.is_empty() {
+                writeln!(xml, "    <edge source=\"{source}\" target=\"{target}\"/>").unwrap();
+            } else {
+                writeln!(xml, "    <edge source=\"{source}\" target=\"{target}\">").unwrap();
+                write_data(&mut xml, attrs, &edge_keys);
+                xml.push_str("    </edge>\n");
+            }
+        }
+        xml.push_str("  </graph>\n</graphml>\n");
+        xml
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Graph;
+
+    static GRAPHML: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<graphml xmlns="http://graphml.graphdrawing.org/xmlns">
+  <key id="k0" for="node" attr.name="color" attr.type="string">
+    <default>yellow</default>
+  </key>
+  <key id="k1" for="edge" attr.name="weight" attr.type="double"/>
+  <graph id="G" edgedefault="undirected">
+    <node id="b"><data key="k0">green</data></node>
+    <node id="a">
+      <graph id="a:" edgedefault="directed">
+        <node id="a::x"/>
+        <edge source="a::x" target="b"/>
+      </graph>
+    </node>
+    <edge source="a" target="b"><data key="k1">1.5</data></edge>
+    <edge source="b" target="b" directed="true"/>
+  </graph>
+</graphml>
+"#;
+
+    #[test]
+    fn graphml_is_flattened_in_document_order() {
+        let graph = Graph::parse_graphml(GRAPHML).unwrap();
+        let nodes: Vec<&str> = graph.nodes().into_iter().map(String::as_str).collect();
+        assert_eq!(nodes, vec!["b", "a", "a::x"]);
+        let id = |node: &str| graph.id_of(&node.to_string()).unwrap();
+        assert_eq!(graph.node_attrs(id("b"))["color"], "green");
+        assert_eq!(graph.node_attrs(id("a"))["color"], "yellow");
+        assert_eq!(graph.edge_attrs(id("a"), id("b")).unwrap()["weight"], "1.5");
+        assert_eq!(graph.edge_attrs(id("b"), id("a")).unwrap()["weight"], "1.5");
+        assert!(graph.has_edge(id("a::x"), id("b")));
+        assert!(!graph.has_edge(id("b"), id("a::x")));
+        assert!(graph.has_edge(id("b"), id("b")));
+    }
+
+    #[test]
+    fn graphml_round_trips() {
+        let graph = Graph::parse_graphml(GRAPHML).unwrap();
+        let xml = graph.to_graphml();
+        let parsed = Graph::parse_graphml(&xml).unwrap();
+        assert_eq!(parsed.nodes(), graph.nodes());
+        for id in graph.node_ids() {
+            assert_eq!(parsed.node_attrs(id), graph.node_attrs(id));
+        }
+        assert_eq!(
+            parsed.edges().collect::<Vec<_>>(),
+            graph.edges().collect::<Vec<_>>()
+        );
+        for (tail, head) in graph.edges() {
+            assert_eq!(parsed.edge_attrs(tail, head), graph.edge_attrs(tail, head));
+        }
+        assert_eq!(xml, parsed.to_graphml());
+    }
+
+    #[test]
+    fn graphml_keeps_whitespace_in_ids_and_data() {
+        let mut graph = Graph::new();
+        graph.add_edge(String::from("a\tb"), String::from("c\r\nd"));
+        let id = graph.node_ids().next().unwrap();
+        graph
+            .node_attrs_mut(id)
+            .insert(String::from("note\n"), String::from(" x\r\n"));
+        let parsed = Graph::parse_graphml(&graph.to_graphml()).unwrap();
+        assert_eq!(parsed.nodes(), graph.nodes());
+        assert_eq!(parsed.node_attrs(id), graph.node_attrs(id));
+    }
+
+    #[test]
+    fn graphml_nested_graphs_inherit_edgedefault() {
+        let graph = Graph::parse_graphml(
+            r#"<graphml><graph edgedefault="undirected"><node id="a"><graph>
+                <node id="b"/><edge source="a" target="b"/>
+            </graph></node></graph></graphml>"#,
+        )
+        .unwrap();
+        assert!(!graph.is_directed());
+        assert_eq!(graph.edges().count(), 1);
+    }
+
+    #[test]
+    fn graphml_rejects_what_it_cannot_represent() {
+        let error = |xml: &str| Graph::parse_graphml(xml).err().unwrap().to_string();
+        assert_eq!(
+            error(r#"<graphml><graph><hyperedge/></graph></graphml>"#),
+            "Unsupported hyperedge on line 1"
+        );
+        assert_eq!(
+            error(r#"<graphml><graph><node id="n"><port name="p"/></node></graph></graphml>"#),
+            "Unsupported port on node \"n\" on line 1"
+        );
+        assert_eq!(
+            error(r#"<graphml><graph><edge source="a" target="b"/></graph></graphml>"#),
+            "Undeclared node \"a\" on line 1"
+        );
+        assert_eq!(
+            error("<graphml>\n  <graph>\n</graphml>"),
+            "expected 'graph' tag, not 'graphml' on line 3"
+        );
+    }
+}
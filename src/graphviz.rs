@@ -0,0 +1,167 @@
+use crate::core::Graph;
+use crate::formatting::quoted;
+use crate::svg::{num, DiagramOptions, Geometry};
+use std::fmt::{Display, Write};
+use std::hash::Hash;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct GraphvizOptions {
+    pub font_name: String,
+    /// Width of a character relative to the font size.
+    pub advance: f64,
+    /// Sizes in points.
+    pub diagram: DiagramOptions,
+}
+
+impl Default for GraphvizOptions {
+    fn default() -> Self {
+        Self {
+            font_name: String::from("Courier"),
+            advance: 0.6,
+            diagram: DiagramOptions::default(),
+        }
+    }
+}
+
+/// Length of arrowheads, in points, with `arrowsize=0.5`.
+const ARROW: f64 = 5.0;
+
+/// Return the control points of a B-spline that goes straight between `points`.
+fn straight(points: &[(f64, f64)]) -> Vec<(f64, f64)> {
+    let mut controls = vec![points[0]];
+    for pair in points.windows(2) {
+        controls.extend([pair[0], pair[1], pair[1]]);
+    }
+    controls
+}
+
+/// Return the `pos` of an edge that follows `controls` and ends in an arrowhead at `tip`.
+fn spline(controls: &[(f64, f64)], tip: (f64, f64)) -> String {
+    let mut pos = format!("e,{},{}", num(tip.0), num(tip.1));
+    for (x, y) in controls {
+        write!(pos, " {},{}", num(*x), num(*y)).unwrap();
+    }
+    pos
+}
+
+impl<T> Graph<T>
+where
+    T: Eq + Hash + Display,
+{
+    /// Return the same diagram as [`Graph::render`] in the DOT format with every position pinned.
+    ///
+    /// Render it with `neato -n2`. Junctions are not drawn.
+    pub fn graphviz(&self, order: &[&T], options: &GraphvizOptions) -> anyhow::Result<String> {
+        let layout = self.diagram_layout(order, &options.diagram)?;
+        let ids: Vec<String> = layout
+            .nodes
+            .iter()
+            .map(|n| quoted(&self.node(n.id).to_string()))
+            .collect();
+        let diagram = &options.diagram;
+        let geometry = Geometry::new(
+            &layout,
+            diagram.gap,
+            diagram.font_size,
+            options.advance,
+            diagram.line_height,
+            diagram.margin,
+        );
+        let height = geometry.height();
+        let y = |row: usize| num(height - geometry.y(row));
+        let inches = |points: f64| num(points / 72.0);
+
+        let mut dot = String::from("digraph {\n");
+        writeln!(dot, "  graph [bb=\"0,0,{},{height}\"];", geometry.width()).unwrap();
+        writeln!(
+            dot,
+            "  node [shape=plaintext, fixedsize=true, margin=0, fontname={}, fontsize={}, \
+             height={}];",
+            quoted(&options.font_name),
+            diagram.font_size,
+            inches(geometry.row_height),
+        )
+        .unwrap();
+        writeln!(dot, "  edge [arrowsize=0.5];").unwrap();
+        for (pos, node) in layout.nodes.iter().enumerate() {
+            let extent = &geometry.extents[pos];
+            let left = geometry.x(extent.label);
+            let right = geometry.x(extent.label + node.width);
+            writeln!(
+                dot,
+                "  {} [label={}, pos=\"{},{}!\", width={}];",
+                ids[pos],
+                quoted(&node.label.replace('\\', "\\\\")),
+                num((left + right) / 2.0),
+                y(pos),
+                inches(right - left),
+            )
+            .unwrap();
+        }
+        for edge in layout.edges.iter() {
+            let (tail, head) = (edge.tail, edge.head);
+            let pos = if tail == head {
+                let x = geometry.x(geometry.extents[tail].label_end) - geometry.char_width / 2.0;
+                let r = geometry.char_width / 2.0;
+                let top = y(tail) + 4.0 * r / 3.0;
+                spline(
+                    &[
+                        (x - r, y(tail)),
+                        (x - r, top),
+                        (x + r, top),
+                        (x + r, y(tail)),
+                    ],
+                    (x + r, y(tail) - ARROW),
+                )
+            } else {
+                let mut points: Vec<(f64, f64)> = geometry
+                    .route(tail, head)
+                    .iter()
+                    .map(|(x, _)| *x)
+                    .zip([y(tail), y(tail), y(head), y(head)])
+                    .collect();
+                let tip = points[3];
+                points[3].0 -= ARROW;
+                spline(&straight(&points), tip)
+            };
+            let style = if head < tail { ", style=dashed" } else { "" };
+            writeln!(
+                dot,
+                "  {} -> {} [pos=\"{pos}\"{style}];",
+                ids[tail], ids[head]
+            )
+            .unwrap();
+        }
+        dot.push_str("}\n");
+        Ok(dot)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{generators, Graph, GraphvizOptions};
+
+    #[test]
+    fn graphviz_pins_every_node_and_edge() {
+        let graph = generators::cyclic();
+        let dot = graph
+            .graphviz(&graph.nodes(), &GraphvizOptions::default())
+            .unwrap();
+        assert_eq!(dot.matches("!\", width=").count(), 9);
+        assert_eq!(dot.matches("[pos=\"e,").count(), 9);
+        assert_eq!(dot.matches("style=dashed").count(), 4);
+        assert!(dot.contains("  \"0\" [label=\"0\", pos=\"29,198.4!\", width=0.12];"));
+    }
+
+    #[test]
+    fn graphviz_ids_are_not_truncated() {
+        let mut graph = Graph::new();
+        graph.add_edge("service-alpha", "service-beta");
+        let mut options = GraphvizOptions::default();
+        options.diagram.max_label_width = 8;
+        let dot = graph.graphviz(&graph.nodes(), &options).unwrap();
+        assert!(dot.contains("  \"service-alpha\" [label=\"service-\""));
+        assert!(dot.contains("  \"service-beta\" [label=\"service-\""));
+        assert!(dot.contains("  \"service-alpha\" -> \"service-beta\" [pos="));
+    }
+}
@@ -0,0 +1,164 @@
+use crate::core::Graph;
+use crate::svg::{drawn, escaped, SvgOptions};
+use std::fmt::{Display, Write};
+use std::hash::Hash;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct HtmlOptions {
+    pub title: String,
+    pub svg: SvgOptions,
+}
+
+impl Default for HtmlOptions {
+    fn default() -> Self {
+        Self {
+            title: String::from("diagv"),
+            svg: SvgOptions::default(),
+        }
+    }
+}
+
+static STYLE: &str = "\
+body{margin:0;font-family:sans-serif}
+#diagv-search{margin:8px;padding:4px;width:20em}
+#diagv-diagram{overflow:auto}
+.node{cursor:pointer}
+.edge.hover .line{stroke:#d33;stroke-width:2.5}
+.junction.hover{fill:#d33}
+.node.hover text,.node.match text{fill:#d33;font-weight:bold}
+svg.focus .node:not(.kept),svg.focus .edge:not(.kept),svg.focus .junction:not(.kept){opacity:.15}
+svg.searching .node:not(.match){opacity:.3}
+";
+
+static SCRIPT: &str = r##"
+(function () {
+  var svg = document.querySelector("#diagv-diagram svg");
+  var search = document.getElementById("diagv-search");
+  var succs = {}, preds = {};
+  edges.forEach(function (e) {
+    (succs[e[0]] = succs[e[0]] || []).push(e[1]);
+    (preds[e[1]] = preds[e[1]] || []).push(e[0]);
+  });
+  function idOf(node) {
+    for (var i = 0; i < node.classList.length; i++) {
+      var m = /^node-(\d+)$/.exec(node.classList[i]);
+      if (m) return Number(m[1]);
+    }
+  }
+  function each(selector, f) {
+    Array.prototype.forEach.call(svg.querySelectorAll(selector), f);
+  }
+  function reach(start, next) {
+    var seen = {}, todo = [start];
+    seen[start] = true;
+    while (todo.length) {
+      (next[todo.pop()] || []).forEach(function (n) {
+        if (!seen[n]) { seen[n] = true; todo.push(n); }
+      });
+    }
+    return seen;
+  }
+  var focused = null;
+  function focus(id) {
+    each(".kept", function (el) { el.classList.remove("kept"); });
+    focused = focused === id ? null : id;
+    svg.classList.toggle("focus", focused !== null);
+    if (focused === null) return;
+    var down = reach(id, succs), up = reach(id, preds);
+    var kept = function (n) { return down[n] || up[n]; };
+    each(".node", function (el) { if (kept(idOf(el))) el.classList.add("kept"); });
+    edges.forEach(function (e) {
+      if ((down[e[0]] && down[e[1]]) || (up[e[0]] && up[e[1]])) {
+        each(".edge-" + e[0] + "-" + e[1], function (el) { el.classList.add("kept"); });
+      }
+    });
+  }
+  each(".node", function (node) {
+    var id = idOf(node);
+    var related = ".node-" + id + ",.from-" + id + ",.to-" + id;
+    node.addEventListener("mouseenter", function () {
+      each(related, function (el) { el.classList.add("hover"); });
+    });
+    node.addEventListener("mouseleave", function () {
+      each(related, function (el) { el.classList.remove("hover"); });
+    });
+    node.addEventListener("click", function (event) {
+      event.stopPropagation();
+      focus(id);
+    });
+  });
+  svg.addEventListener("click", function () { if (focused !== null) focus(focused); });
+  search.addEventListener("input", function () {
+    var query = search.value.toLowerCase();
+    svg.classList.toggle("searching", query !== "");
+    each(".node", function (node) {
+      var label = node.querySelector("title").textContent.toLowerCase();
+      node.classList.toggle("match", query !== "" && label.indexOf(query) !== -1);
+    });
+  });
+})();
+"##;
+
+impl<T> Graph<T>
+where
+    T: Eq + Hash + Display,
+{
+    /// Export the same diagram as [`Graph::svg`] as a standalone, interactive HTML page.
+    ///
+    /// Hovering a node highlights its edges, clicking it fades everything that is neither an
+    /// ancestor nor a descendant, and the search box highlights nodes by label.
+    pub fn html(&self, order: &[&T], options: &HtmlOptions) -> anyhow::Result<String> {
+        let layout = self.diagram_layout(order, &options.svg.diagram)?;
+
+        let edges: Vec<String> = layout
+            .edges
+            .iter()
+            .map(|e| {
+                let t = layout.nodes[e.tail].id.index();
+                let h = layout.nodes[e.head].id.index();
+                format!("[{t},{h}]")
+            })
+            .collect();
+
+        let mut html = String::new();
+        writeln!(html, "<!DOCTYPE html>").unwrap();
+        writeln!(html, "<html>").unwrap();
+        writeln!(html, "<head>").unwrap();
+        writeln!(html, "<meta charset=\"utf-8\">").unwrap();
+        writeln!(html, "<title>{}</title>", escaped(&options.title)).unwrap();
+        writeln!(html, "<style>{STYLE}</style>").unwrap();
+        writeln!(html, "</head>").unwrap();
+        writeln!(html, "<body>").unwrap();
+        writeln!(
+            html,
+            "<input id=\"diagv-search\" type=\"search\" placeholder=\"Search nodes\">"
+        )
+        .unwrap();
+        writeln!(html, "<div id=\"diagv-diagram\">").unwrap();
+        html.push_str(&drawn(&layout, &options.svg));
+        writeln!(html, "</div>").unwrap();
+        writeln!(html, "<script>").unwrap();
+        writeln!(html, "var edges = [{}];", edges.join(",")).unwrap();
+        html.push_str(SCRIPT);
+        writeln!(html, "</script>").unwrap();
+        writeln!(html, "</body>").unwrap();
+        writeln!(html, "</html>").unwrap();
+        Ok(html)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::generators;
+    use crate::HtmlOptions;
+
+    #[test]
+    fn html_embeds_diagram_and_edges() {
+        let graph = generators::diagv();
+        let html = graph.html(&graph.nodes(), &HtmlOptions::default()).unwrap();
+        assert!(html.contains("<svg "));
+        assert!(html.contains("var edges = [[0,2],[1,2],[1,3],[2,4],[3,4]];"));
+        assert!(!html.contains(" src="));
+        assert!(!html.contains(" href="));
+    }
+}
@@ -0,0 +1,386 @@
+use crate::core::{Attributes, Graph};
+use crate::error::{ParseError, Span};
+use serde::de::{MapAccess, SeqAccess, Visitor};
+use serde::ser::SerializeMap;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde_json::{Map, Value};
+use std::fmt::{self, Display};
+
+#[derive(Deserialize, Serialize)]
+struct Document {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    graph: Option<JgfGraph>,
+    #[serde(default, skip_serializing)]
+    graphs: Vec<JgfGraph>,
+}
+
+fn directed() -> bool {
+    true
+}
+
+#[derive(Deserialize, Serialize)]
+struct JgfGraph {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    label: Option<String>,
+    #[serde(default = "directed")]
+    directed: bool,
+    #[serde(default, skip_serializing_if = "Map::is_empty")]
+    metadata: Map<String, Value>,
+    #[serde(default)]
+    nodes: Nodes,
+    #[serde(default)]
+    edges: Vec<JgfEdge>,
+    #[serde(default, skip_serializing)]
+    hyperedges: Vec<Value>,
+}
+
+#[derive(Deserialize, Serialize)]
+struct JgfNode {
+    /// Only given when the nodes are in an array, as in version 1 of the format.
+    #[serde(default, skip_serializing)]
+    id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    label: Option<String>,
+    #[serde(default, skip_serializing_if = "Map::is_empty")]
+    metadata: Map<String, Value>,
+}
+
+#[derive(Deserialize, Serialize)]
+struct JgfEdge {
+    source: String,
+    target: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    relation: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    directed: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    label: Option<String>,
+    #[serde(default, skip_serializing_if = "Map::is_empty")]
+    metadata: Map<String, Value>,
+}
+
+/// Nodes by id in document order, whether given as an object or as an array.
+#[derive(Default)]
+struct Nodes(Vec<(String, JgfNode)>);
+
+impl Serialize for Nodes {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(self.0.len()))?;
+        for (id, node) in self.0.iter() {
+            map.serialize_entry(id, node)?;
+        }
+        map.end()
+    }
+}
+
+struct NodesVisitor;
+
+impl<'de> Visitor<'de> for NodesVisitor {
+    type Value = Nodes;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("an object or an array of nodes")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Nodes, A::Error> {
+        let mut nodes = Vec::new();
+        while let Some(entry) = map.next_entry()? {
+            nodes.push(entry);
+        }
+        Ok(Nodes(nodes))
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Nodes, A::Error> {
+        let mut nodes = Vec::new();
+        while let Some(node) = seq.next_element::<JgfNode>()? {
+            match node.id.clone() {
+                Some(id) => nodes.push((id, node)),
+                None => return Err(serde::de::Error::missing_field("id")),
+            }
+        }
+        Ok(Nodes(nodes))
+    }
+}
+
+impl<'de> Deserialize<'de> for Nodes {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(NodesVisitor)
+    }
+}
+
+/// Return the metadata as attributes, with any label under `label`.
+///
+/// Values that are not strings are kept as JSON.
+fn attributes(label: Option<String>, metadata: Map<String, Value>) -> Attributes {
+    let mut attrs: Attributes = metadata
+        .into_iter()
+        .map(|(k, v)| match v {
+            Value::String(v) => (k, v),
+            v => (k, v.to_string()),
+        })
+        .collect();
+    if let Some(label) = label {
+        attrs.insert(String::from("label"), label);
+    }
+    attrs
+}
+
+/// Return the label and the remaining attributes as metadata.
+fn split_label(attrs: &Attributes, reserved: &[&str]) -> (Option<String>, Map<String, Value>) {
+    let metadata = attrs
+        .iter()
+        .filter(|(k, _)| k.as_str() != "label" && !reserved.contains(&k.as_str()))
+        .map(|(k, v)| (k.clone(), Value::String(v.clone())))
+        .collect();
+    (attrs.get("label").cloned(), metadata)
+}
+
+/// Return the span of the `nth` occurrence of `text` in `s`, or the start of `s` if there is none.
+fn find(s: &str, text: &str, nth: usize) -> Span {
+    match s.match_indices(text).nth(nth) {
+        Some((start, _)) => Span::new(s, start, start + text.len()),
+        None => Span::new(s, 0, 0),
+    }
+}
+
+/// Return the span of the `nth` occurrence of `id` as a JSON string.
+fn find_id(s: &str, id: &str, nth: usize) -> Span {
+    find(s, &serde_json::to_string(id).unwrap(), nth)
+}
+
+/// Return the byte offset of the line and the column, counted in bytes, of a JSON error.
+fn offset(s: &str, line: usize, column: usize) -> usize {
+    let line_start: usize = s
+        .split_inclusive('\n')
+        .take(line.saturating_sub(1))
+        .map(str::len)
+        .sum();
+    let mut offset = (line_start + column.saturating_sub(1)).min(s.len());
+    while !s.is_char_boundary(offset) {
+        offset -= 1;
+    }
+    offset
+}
+
+impl Graph<String> {
+    /// Parse a document in the JSON Graph Format that holds one graph.
+    ///
+    /// Labels and metadata become attributes, as does the `relation` of edges. If every edge is
+    /// undirected, the graph is undirected with one edge for each pair of nodes, as with
+    /// [`Graph::parse_dot`]. Otherwise undirected edges become one edge in each direction.
+    pub fn parse_jgf(s: &str) -> Result<Self, ParseError> {
+        let mut document: Document = match serde_json::from_str(s) {
+            Ok(document) => document,
+            Err(error) => {
+                let start = offset(s, error.line(), error.column());
+                let message = error.to_string();
+                let suffix = format!(" at line {} column {}", error.line(), error.column());
+                return Err(ParseError::Malformed {
+                    message: message.trim_end_matches(&suffix).to_string(),
+                    span: Span::new(s, start, start),
+                });
+            }
+        };
+        let jgf = match (document.graph, document.graphs.len()) {
+            (Some(graph), 0) => graph,
+            (None, 1) => document.graphs.remove(0),
+            (graph, count) => {
+                let count = count + graph.is_some() as usize;
+                return Err(ParseError::Expected {
+                    expected: String::from("one graph"),
+                    found: (count > 0).then(|| format!("{count} graphs")),
+                    span: find(s, "\"graphs\"", 0),
+                });
+            }
+        };
+        if !jgf.hyperedges.is_empty() {
+            return Err(ParseError::Unsupported {
+                what: String::from("hyperedge"),
+                span: find(s, "\"hyperedges\"", 0),
+            });
+        }
+
+        let mut graph = Self::new();
+        *graph.attrs_mut() = attributes(jgf.label, jgf.metadata);
+        for (node, jgf_node) in jgf.nodes.0 {
+            if !graph.add_node(node.clone()) {
+                return Err(ParseError::Duplicate {
+                    span: find_id(s, &node, 1),
+                    node,
+                });
+            }
+            let id = graph.id_of(&node).unwrap();
+            *graph.node_attrs_mut(id) = attributes(jgf_node.label, jgf_node.metadata);
+        }
+        let undirected = !jgf.directed && jgf.edges.iter().all(|e| e.directed != Some(true));
+        for edge in jgf.edges {
+            let mut attrs = attributes(edge.label, edge.metadata);
+            if let Some(relation) = edge.relation {
+                attrs.insert(String::from("relation"), relation);
+            }
+            let ends = [(&edge.source, &edge.target), (&edge.target, &edge.source)];
+            let ends = if edge.directed.unwrap_or(jgf.directed) || undirected {
+                &ends[..1]
+            } else {
+                &ends[..]
+            };
+            for (tail, head) in ends {
+                let (t, h) = match (graph.id_of(tail), graph.id_of(head)) {
+                    (Some(t), Some(h)) => (t, h),
+                    (t, _) => {
+                        let node = if t.is_none() { tail } else { head };
+                        return Err(ParseError::Undeclared {
+                            node: node.to_string(),
+                            span: find_id(s, node, 0),
+                        });
+                    }
+                };
+                let (t, h) = if undirected && graph.has_edge(h, t) {
+                    (h, t)
+                } else {
+                    graph.add_edge(tail.to_string(), head.to_string());
+                    (t, h)
+                };
+                graph.edge_attrs_mut(t, h).unwrap().extend(attrs.clone());
+            }
+        }
+        graph.set_directed(!undirected);
+        Ok(graph)
+    }
+}
+
+impl<T> Graph<T>
+where
+    T: Display,
+{
+    /// Return the graph in the JSON Graph Format, such that [`Graph::parse_jgf`] gives it back.
+    pub fn to_jgf(&self) -> String {
+        let ids: Vec<String> = self
+            .node_ids()
+            .map(|id| self.node(id).to_string())
+            .collect();
+        let (label, metadata) = split_label(self.attrs(), &[]);
+        let nodes = self
+            .node_ids()
+            .map(|id| {
+                let (label, metadata) = split_label(self.node_attrs(id), &[]);
+                let node = JgfNode {
+                    id: None,
+                    label,
+                    metadata,
+                };
+                (ids[id.index()].clone(), node)
+            })
+            .collect();
+        let edges = self
+            .edges()
+            .map(|(tail, head)| {
+                let attrs = self.edge_attrs(tail, head).unwrap();
+                let (label, metadata) = split_label(attrs, &["relation"]);
+                JgfEdge {
+                    source: ids[tail.index()].clone(),
+                    target: ids[head.index()].clone(),
+                    relation: attrs.get("relation").cloned(),
+                    directed: None,
+                    label,
+                    metadata,
+                }
+            })
+            .collect();
+        let document = Document {
+            graph: Some(JgfGraph {
+                label,
+                directed: self.is_directed(),
+                metadata,
+                nodes: Nodes(nodes),
+                edges,
+                hyperedges: Vec::new(),
+            }),
+            graphs: Vec::new(),
+        };
+        serde_json::to_string_pretty(&document).unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Graph;
+
+    static JGF: &str = r#"{
+  "graph": {
+    "label": "Pipeline",
+    "metadata": {"owner": "ci", "version": 2},
+    "nodes": {
+      "build": {"label": "Build"},
+      "test": {"metadata": {"flaky": true}},
+      "deploy": {}
+    },
+    "edges": [
+      {"source": "build", "target": "test", "relation": "then"},
+      {"source": "test", "target": "deploy", "label": "on success"},
+      {"source": "deploy", "target": "build", "directed": false}
+    ]
+  }
+}"#;
+
+    #[test]
+    fn jgf_keeps_order_labels_and_metadata() {
+        let graph = Graph::parse_jgf(JGF).unwrap();
+        let nodes: Vec<&str> = graph.nodes().into_iter().map(String::as_str).collect();
+        assert_eq!(nodes, vec!["build", "test", "deploy"]);
+        assert_eq!(graph.attrs()["label"], "Pipeline");
+        assert_eq!(graph.attrs()["version"], "2");
+        let id = |node: &str| graph.id_of(&node.to_string()).unwrap();
+        assert_eq!(graph.node_attrs(id("build"))["label"], "Build");
+        assert_eq!(graph.node_attrs(id("test"))["flaky"], "true");
+        let attrs = graph.edge_attrs(id("build"), id("test")).unwrap();
+        assert_eq!(attrs["relation"], "then");
+        assert!(graph.has_edge(id("build"), id("deploy")));
+        assert_eq!(graph.edges().count(), 4);
+    }
+
+    #[test]
+    fn jgf_round_trips() {
+        let graph = Graph::parse_jgf(JGF).unwrap();
+        let jgf = graph.to_jgf();
+        let parsed = Graph::parse_jgf(&jgf).unwrap();
+        assert_eq!(parsed.nodes(), graph.nodes());
+        assert_eq!(parsed.attrs(), graph.attrs());
+        for id in graph.node_ids() {
+            assert_eq!(parsed.node_attrs(id), graph.node_attrs(id));
+        }
+        for (tail, head) in graph.edges() {
+            assert_eq!(parsed.edge_attrs(tail, head), graph.edge_attrs(tail, head));
+        }
+        assert_eq!(jgf, parsed.to_jgf());
+    }
+
+    #[test]
+    fn jgf_accepts_node_arrays_and_rejects_missing_nodes() {
+        let v1 = r#"{"graph": {"nodes": [{"id": "b"}, {"id": "a"}], "edges": []}}"#;
+        let graph = Graph::parse_jgf(v1).unwrap();
+        assert_eq!(graph.nodes(), vec!["b", "a"]);
+        let missing =
+            r#"{"graph": {"nodes": {"a": {}}, "edges": [{"source": "a", "target": "b"}]}}"#;
+        let error = Graph::parse_jgf(missing).err().unwrap();
+        assert_eq!(error.to_string(), "Undeclared node \"b\" on line 1");
+        assert_eq!(error.span().column, 68);
+    }
+
+    #[test]
+    fn jgf_locates_errors() {
+        let error = |json: &str| Graph::parse_jgf(json).err().unwrap();
+        let malformed = error(
+            "{\"graph\": {\n  \"nodes\": {\"a\": {}},\n  \"edges\": [{\"source\": \"a\"}]\n}}",
+        );
+        assert_eq!(malformed.to_string(), "missing field `target` on line 3");
+        let duplicate = error("{\"graph\": {\"nodes\": [{\"id\": \"a\"},\n {\"id\": \"a\"}]}}");
+        assert_eq!(
+            duplicate.to_string(),
+            "Node \"a\" is declared again on line 2"
+        );
+        assert_eq!(duplicate.span().column, 9);
+        let graphs = error("{\"graphs\": []}");
+        assert_eq!(graphs.to_string(), "Expected one graph on line 1");
+    }
+}
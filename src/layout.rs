@@ -0,0 +1,463 @@
+use crate::core::{Graph, NodeId};
+use anyhow::bail;
+use std::fmt::Display;
+use std::hash::Hash;
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// Placement of the nodes and edges of a graph with the nodes on the diagonal of a grid.
+///
+/// Rows and columns are both positions in the order that the layout was made with.
+/// Every edge runs horizontally along the row of its tail to the column of its head and then
+/// vertically along that column to the head.
+/// Hence edges to later rows run above the diagonal and edges to earlier rows, back edges, below it.
+/// Each column has a lane on the left, where the vertical lines run, followed by the label of
+/// its node.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Layout {
+    /// Nodes in the order that they are placed on the diagonal.
+    pub nodes: Vec<Placement>,
+    /// Edges ordered by tail and then by head.
+    ///
+    /// Self-loops are included, with `tail == head`.
+    pub edges: Vec<Route>,
+    /// Points where edges turn or arrive, ordered by row and then by column.
+    pub junctions: Vec<Junction>,
+    /// Cells of each row up to and including the last cell that has anything to draw.
+    pub rows: Vec<Vec<Cell>>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Placement {
+    pub id: NodeId,
+    pub label: String,
+    /// Width of the column, not counting the lane.
+    pub width: usize,
+    /// Whether the column has a lane.
+    ///
+    /// Only the first column may go without one, when nothing arrives at its node.
+    pub lane: bool,
+    /// Whether the node has an edge to itself.
+    ///
+    /// Such edges are not routed through the grid but marked next to the label.
+    pub self_loop: bool,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Route {
+    /// Position of the tail.
+    pub tail: usize,
+    /// Position of the head.
+    pub head: usize,
+    /// The part along the row of the tail.
+    pub horizontal: Segment,
+    /// The part along the lane of the head.
+    pub vertical: Segment,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Segment {
+    /// Row of a horizontal segment or column of a vertical segment.
+    pub at: usize,
+    pub from: usize,
+    pub to: usize,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Junction {
+    pub row: usize,
+    pub col: usize,
+    /// Indices into [`Layout::edges`] of the edges that turn or arrive here.
+    pub edges: Vec<usize>,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Cell {
+    pub lane: Lane,
+    /// Whether a line runs from the lane to the label, or through the column if there is no label.
+    pub before: bool,
+    /// Whether a line runs from the label to the next lane, or through the column if there is no
+    /// label.
+    pub after: bool,
+}
+
+/// Directions that lines leave the lane of a cell in.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Lane {
+    pub up: bool,
+    pub down: bool,
+    pub left: bool,
+    pub right: bool,
+    /// Whether the lines are joined rather than just crossing.
+    pub junction: bool,
+}
+
+impl Lane {
+    pub fn is_blank(&self) -> bool {
+        !(self.up || self.down || self.left || self.right)
+    }
+}
+
+/// Horizontal position of the parts of a column, in characters from the left edge.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Extent {
+    pub lane: usize,
+    pub label: usize,
+    /// Where the label, including any self-loop marker, ends.
+    pub label_end: usize,
+    /// Where the next column starts.
+    pub end: usize,
+}
+
+impl Layout {
+    /// Return the number of rows and columns.
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    /// Return where the parts of every column go when drawn with a monospace font.
+    ///
+    /// Lanes and self-loop markers are one character wide and separated from labels by `gap`.
+    pub fn extents(&self, gap: usize) -> Vec<Extent> {
+        let mut x = 0;
+        self.nodes
+            .iter()
+            .map(|node| {
+                let lane = x;
+                let label = if node.lane { lane + 1 + gap } else { lane };
+                let label_end = label + node.width + if node.self_loop { 1 } else { 0 };
+                x = label_end + gap;
+                Extent {
+                    lane,
+                    label,
+                    label_end,
+                    end: x,
+                }
+            })
+            .collect()
+    }
+
+    /// Shorten every label wider than `max_width`, ending it with `marker`.
+    ///
+    /// Widths are measured in terminal columns and labels are only cut between grapheme clusters.
+    pub fn truncate_labels(&mut self, max_width: usize, marker: &str) {
+        let (marker, marker_width) = truncated(marker, max_width);
+        for node in self.nodes.iter_mut() {
+            if max_width < node.width {
+                let (mut label, width) = truncated(&node.label, max_width - marker_width);
+                label.push_str(&marker);
+                node.label = label;
+                node.width = width + marker_width;
+            }
+        }
+    }
+}
+
+/// Return the longest prefix of `text` that fits in `max_width` columns, and its width.
+fn truncated(text: &str, max_width: usize) -> (String, usize) {
+    let mut result = String::new();
+    let mut width = 0;
+    for grapheme in text.graphemes(true) {
+        let w = grapheme.width();
+        if max_width < width + w {
+            break;
+        }
+        result.push_str(grapheme);
+        width += w;
+    }
+    (result, width)
+}
+
+/// Where the edges of a graph run when its nodes are placed on the diagonal in a given order.
+struct Spans {
+    /// Positions of the heads of each row, in ascending order.
+    heads: Vec<Vec<usize>>,
+    /// First and last row with an edge to each column.
+    tails: Vec<Option<(usize, usize)>>,
+    /// Rightmost column reached by the edges from each row, or the row itself.
+    right: Vec<usize>,
+    /// Rightmost column reached by the edges from all rows above each row.
+    reach: Vec<usize>,
+    /// Whether each row has an edge to itself, which is not included in `heads`.
+    loops: Vec<bool>,
+}
+
+impl Spans {
+    fn new<T: Eq + Hash>(graph: &Graph<T>, order: &[&T]) -> anyhow::Result<(Self, Vec<NodeId>)> {
+        let n = order.len();
+        if n != graph.len() {
+            bail!("Order must contain every node exactly once");
+        }
+        let mut ids = Vec::with_capacity(n);
+        let mut positions = vec![None; n];
+        for (i, node) in order.iter().enumerate() {
+            let id = match graph.id_of(node) {
+                Some(id) => id,
+                None => bail!("Order contains a node that is not in the graph"),
+            };
+            if positions[id.index()].replace(i).is_some() {
+                bail!("Order must contain every node exactly once");
+            }
+            ids.push(id);
+        }
+
+        let mut heads = Vec::with_capacity(n);
+        let mut tails: Vec<Option<(usize, usize)>> = vec![None; n];
+        let mut right = Vec::with_capacity(n);
+        let mut reach = Vec::with_capacity(n);
+        let mut loops = Vec::with_capacity(n);
+        let mut above = 0;
+        for (row, id) in ids.iter().enumerate() {
+            let mut hs: Vec<usize> = graph
+                .successors(*id)
+                .map(|h| positions[h.index()].unwrap())
+                .collect();
+            hs.sort_unstable();
+            match hs.binary_search(&row) {
+                Ok(i) => {
+                    hs.remove(i);
+                    loops.push(true);
+                }
+                Err(_) => loops.push(false),
+            }
+            for h in hs.iter() {
+                let span = tails[*h].get_or_insert((row, row));
+                span.1 = row;
+            }
+            reach.push(above);
+            right.push(hs.last().map_or(row, |h| row.max(*h)));
+            above = above.max(right[row]);
+            heads.push(hs);
+        }
+
+        Ok((
+            Self {
+                heads,
+                tails,
+                right,
+                reach,
+                loops,
+            },
+            ids,
+        ))
+    }
+
+    /// Return the rightmost column that has anything to draw in `row`.
+    fn end(&self, row: usize) -> usize {
+        row.max(self.right[row]).max(self.reach[row])
+    }
+
+    fn is_head(&self, row: usize, col: usize) -> bool {
+        self.heads[row].binary_search(&col).is_ok()
+    }
+
+    fn leftmost_head(&self, row: usize) -> Option<usize> {
+        self.heads[row].first().copied()
+    }
+
+    fn cell(&self, row: usize, col: usize) -> Cell {
+        let tails = self.tails[col];
+        if col < row {
+            let junction = self.is_head(row, col);
+            let horizontal = matches!(self.leftmost_head(row), Some(h) if h <= col);
+            Cell {
+                lane: Lane {
+                    up: matches!(tails, Some((_, last)) if row <= last),
+                    down: matches!(tails, Some((_, last)) if row < last),
+                    left: matches!(self.leftmost_head(row), Some(h) if h < col),
+                    right: horizontal,
+                    junction,
+                },
+                before: horizontal,
+                after: horizontal,
+            }
+        } else if col == row {
+            let up = matches!(tails, Some((first, _)) if first < row);
+            let down = matches!(tails, Some((_, last)) if row < last);
+            let left = matches!(self.leftmost_head(row), Some(h) if h < col);
+            Cell {
+                lane: Lane {
+                    up,
+                    down,
+                    left,
+                    right: up || down || left,
+                    junction: up || down,
+                },
+                before: up || down || left,
+                after: col < self.right[row],
+            }
+        } else {
+            let junction = self.is_head(row, col);
+            let up = matches!(tails, Some((first, _)) if first < row);
+            let horizontal = col < self.right[row];
+            Cell {
+                lane: Lane {
+                    up,
+                    down: up || junction,
+                    left: col <= self.right[row],
+                    right: horizontal,
+                    junction,
+                },
+                before: horizontal,
+                after: horizontal,
+            }
+        }
+    }
+}
+
+impl<T> Graph<T>
+where
+    T: Eq + Hash + Display,
+{
+    /// Return the layout of [`Graph::layout`] with its labels truncated to `max_width`.
+    pub(crate) fn truncated_layout(
+        &self,
+        order: &[&T],
+        max_width: usize,
+        marker: &str,
+    ) -> anyhow::Result<Layout> {
+        let mut layout = self.layout(order)?;
+        layout.truncate_labels(max_width, marker);
+        Ok(layout)
+    }
+
+    /// Place the nodes on the diagonal in the given order and route the edges between them.
+    ///
+    /// Nodes are labelled by their `label` attribute if they have one and by `Display` otherwise.
+    pub fn layout(&self, order: &[&T]) -> anyhow::Result<Layout> {
+        let (spans, ids) = Spans::new(self, order)?;
+
+        let nodes = ids
+            .into_iter()
+            .zip(order)
+            .enumerate()
+            .map(|(i, (id, node))| {
+                let label = match self.node_attrs(id).get("label") {
+                    Some(label) => label.clone(),
+                    None => format!("{}", node),
+                };
+                Placement {
+                    id,
+                    width: label.width(),
+                    label,
+                    lane: i != 0 || spans.tails[i].is_some(),
+                    self_loop: spans.loops[i],
+                }
+            })
+            .collect();
+
+        let mut edges = Vec::new();
+        let mut turns = vec![Vec::new(); order.len()];
+        let mut arrivals = vec![Vec::new(); order.len()];
+        for (tail, hs) in spans.heads.iter().enumerate() {
+            let split = hs.partition_point(|h| *h < tail);
+            let own = if spans.loops[tail] { Some(tail) } else { None };
+            let heads = hs[..split].iter().copied().chain(own);
+            for head in heads.chain(hs[split..].iter().copied()) {
+                if head != tail {
+                    turns[tail].push((head, edges.len()));
+                    arrivals[head].push(edges.len());
+                }
+                edges.push(Route {
+                    tail,
+                    head,
+                    horizontal: Segment {
+                        at: tail,
+                        from: tail,
+                        to: head,
+                    },
+                    vertical: Segment {
+                        at: head,
+                        from: tail,
+                        to: head,
+                    },
+                });
+            }
+        }
+
+        let mut junctions = Vec::new();
+        let mut rows = Vec::with_capacity(order.len());
+        for (row, turns) in turns.into_iter().enumerate() {
+            let split = turns.partition_point(|(col, _)| *col < row);
+            let junction = |(col, edge): &(usize, usize)| Junction {
+                row,
+                col: *col,
+                edges: vec![*edge],
+            };
+            junctions.extend(turns[..split].iter().map(junction));
+            if !arrivals[row].is_empty() {
+                junctions.push(Junction {
+                    row,
+                    col: row,
+                    edges: arrivals[row].clone(),
+                });
+            }
+            junctions.extend(turns[split..].iter().map(junction));
+
+            rows.push(
+                (0..=spans.end(row))
+                    .map(|col| spans.cell(row, col))
+                    .collect(),
+            );
+        }
+
+        Ok(Layout {
+            nodes,
+            edges,
+            junctions,
+            rows,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::generators;
+
+    #[test]
+    fn junctions_belong_to_their_edges() {
+        let graph = generators::diagv();
+        let layout = graph.layout(&graph.nodes()).unwrap();
+        let v = layout.junctions.last().unwrap();
+        assert_eq!((v.row, v.col), (4, 4));
+        for e in v.edges.iter() {
+            assert_eq!(layout.edges[*e].head, 4);
+        }
+        assert_eq!(v.edges.len(), 2);
+        assert_eq!(layout.junctions.len(), layout.edges.len() + 3);
+    }
+
+    #[test]
+    fn rows_end_at_last_line() {
+        let graph = generators::diagv();
+        let layout = graph.layout(&graph.nodes()).unwrap();
+        let widths: Vec<_> = layout.rows.iter().map(|r| r.len()).collect();
+        assert_eq!(widths, vec![3, 4, 5, 5, 5]);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn layout_round_trips_through_json() {
+        let graph = generators::cyclic();
+        let layout = graph.layout(&graph.nodes()).unwrap();
+        let json = serde_json::to_string(&layout).unwrap();
+        assert_eq!(
+            serde_json::from_str::<crate::Layout>(&json).unwrap(),
+            layout
+        );
+    }
+}
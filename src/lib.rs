@@ -1,9 +1,33 @@
 mod core;
+mod dot;
+mod error;
+mod formatting;
 mod generating;
+mod graphml;
+mod graphviz;
+mod html;
+#[cfg(feature = "serde")]
+mod jgf;
+mod layout;
+mod matrix;
+mod mermaid;
 mod parsing;
+#[cfg(feature = "raster")]
+mod raster;
 mod rendering;
+#[cfg(feature = "serde")]
+mod serialization;
+mod svg;
 
 pub mod generators {
     pub use crate::generating::*;
 }
-pub use core::Graph;
+pub use core::{Attributes, Graph, NodeId, Subgraph};
+pub use error::{ParseError, Span};
+pub use graphviz::GraphvizOptions;
+pub use html::HtmlOptions;
+pub use layout::{Cell, Extent, Junction, Lane, Layout, Placement, Route, Segment};
+#[cfg(feature = "raster")]
+pub use raster::PngOptions;
+pub use rendering::{ColorMode, Glyphs, LineEnding, RenderOptions};
+pub use svg::{DiagramOptions, SvgOptions};
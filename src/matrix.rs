@@ -0,0 +1,193 @@
+use crate::core::Graph;
+use crate::error::{ParseError, Span};
+use anyhow::bail;
+use std::collections::HashSet;
+use std::fmt::Display;
+use std::hash::Hash;
+
+impl Graph<String> {
+    /// Parse a square CSV adjacency matrix whose first row and column name the nodes.
+    ///
+    /// The cell in the row of one node and the column of another holds an edge from the former to
+    /// the latter if it is neither empty nor zero. Numbers are kept in the `weight` attribute.
+    pub fn parse_adjacency_matrix(s: &str) -> Result<Self, ParseError> {
+        let mut reader = csv::ReaderBuilder::new()
+            .has_headers(false)
+            .flexible(true)
+            .from_reader(s.as_bytes());
+        // The rest of the line that a record starts on, or the end of the input.
+        let line = |position: Option<&csv::Position>| {
+            let start = position.map_or(s.len(), |p| p.byte() as usize);
+            Span::new(s, start, s.len())
+        };
+        let mut rows = Vec::new();
+        for record in reader.records() {
+            match record {
+                Ok(record) => rows.push(record),
+                Err(error) => {
+                    return Err(ParseError::Malformed {
+                        span: line(error.position()),
+                        message: error.to_string(),
+                    })
+                }
+            }
+        }
+        let expected = |expected: String, found: Option<String>, span| ParseError::Expected {
+            expected,
+            found,
+            span,
+        };
+        let (header, rows) = match rows.split_first() {
+            Some(split) => split,
+            None => return Err(expected(String::from("a header row"), None, line(None))),
+        };
+        let names: Vec<&str> = header.iter().skip(1).map(str::trim).collect();
+        if rows.len() != names.len() {
+            return Err(expected(
+                format!("{} rows after the header", names.len()),
+                Some(rows.len().to_string()),
+                line(rows.get(names.len()).and_then(|r| r.position())),
+            ));
+        }
+
+        let mut graph = Self::new();
+        for name in names.iter() {
+            if !graph.add_node(name.to_string()) {
+                return Err(ParseError::Duplicate {
+                    node: name.to_string(),
+                    span: line(header.position()),
+                });
+            }
+        }
+        for (row, record) in rows.iter().enumerate() {
+            let span = || line(record.position());
+            if record.len() != names.len() + 1 {
+                return Err(expected(
+                    format!("{} cells", names.len() + 1),
+                    Some(record.len().to_string()),
+                    span(),
+                ));
+            }
+            let tail = record[0].trim();
+            if tail != names[row] {
+                return Err(expected(
+                    format!("the row of {:?}", names[row]),
+                    Some(format!("{tail:?}")),
+                    span(),
+                ));
+            }
+            for (col, cell) in record.iter().skip(1).map(str::trim).enumerate() {
+                let weight = cell.parse::<f64>().ok();
+                if weight.is_some_and(|w| !w.is_finite()) {
+                    return Err(expected(
+                        String::from("a finite weight"),
+                        Some(format!("{cell:?}")),
+                        span(),
+                    ));
+                }
+                if cell.is_empty() || weight == Some(0.0) {
+                    continue;
+                }
+                let head = names[col];
+                graph.add_edge(tail.to_string(), head.to_string());
+                if weight.is_some() {
+                    let (t, h) = (graph.id_of(&tail.into()), graph.id_of(&head.into()));
+                    graph
+                        .edge_attrs_mut(t.unwrap(), h.unwrap())
+                        .unwrap()
+                        .insert(String::from("weight"), cell.to_string());
+                }
+            }
+        }
+        Ok(graph)
+    }
+}
+
+impl<T> Graph<T>
+where
+    T: Eq + Hash + Display,
+{
+    /// Return the adjacency matrix of the graph as CSV, with nodes in the given order.
+    ///
+    /// Edges are written as their `weight` attribute if they have one and as `1` otherwise.
+    pub fn to_adjacency_matrix(&self, order: &[&T]) -> anyhow::Result<String> {
+        let mut ids = Vec::new();
+        for node in order {
+            match self.id_of(node) {
+                Some(id) => ids.push(id),
+                None => bail!("Order contains a node that is not in the graph"),
+            }
+        }
+        if ids.len() != self.len() || ids.iter().collect::<HashSet<_>>().len() != ids.len() {
+            bail!("Order must contain every node exactly once");
+        }
+
+        let mut writer = csv::Writer::from_writer(Vec::new());
+        let names: Vec<String> = order.iter().map(|n| n.to_string()).collect();
+        writer.write_record(std::iter::once("").chain(names.iter().map(String::as_str)))?;
+        for (tail, name) in ids.iter().zip(names.iter()) {
+            let cells = ids.iter().map(|head| match self.edge_attrs(*tail, *head) {
+                Some(attrs) => attrs.get("weight").map_or("1", String::as_str),
+                None => "",
+            });
+            writer.write_record(std::iter::once(name.as_str()).chain(cells))?;
+        }
+        Ok(String::from_utf8(writer.into_inner()?)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{generators, Graph};
+
+    #[test]
+    fn adjacency_matrix_round_trips_in_any_order() {
+        let graph = generators::sonic(2);
+        let order = graph.nodes().into_iter().rev().collect::<Vec<_>>();
+        let csv = graph.to_adjacency_matrix(&order).unwrap();
+        let parsed = Graph::parse_adjacency_matrix(&csv).unwrap();
+        assert_eq!(parsed.nodes(), order);
+        assert_eq!(parsed.to_adjacency_matrix(&order).unwrap(), csv);
+    }
+
+    #[test]
+    fn adjacency_matrix_keeps_weights() {
+        let csv = "DSM,\"ui, web\",api,db\n\"ui, web\",,2,\napi,0,,x\ndb,,,\n";
+        let graph = Graph::parse_adjacency_matrix(csv).unwrap();
+        assert_eq!(graph.nodes(), vec!["ui, web", "api", "db"]);
+        let ids: Vec<_> = graph.node_ids().collect();
+        assert_eq!(graph.edge_attrs(ids[0], ids[1]).unwrap()["weight"], "2");
+        assert!(graph.edge_attrs(ids[1], ids[2]).unwrap().is_empty());
+        assert_eq!(graph.edges().count(), 2);
+        assert_eq!(
+            graph.to_adjacency_matrix(&graph.nodes()).unwrap(),
+            ",\"ui, web\",api,db\n\"ui, web\",,2,\napi,,,1\ndb,,,\n"
+        );
+    }
+
+    #[test]
+    fn adjacency_matrix_must_be_square() {
+        let error = Graph::parse_adjacency_matrix(",a,b\na,,1\nb,1\n")
+            .err()
+            .unwrap();
+        assert_eq!(error.to_string(), "Expected 3 cells but found 2 on line 3");
+    }
+
+    #[test]
+    fn adjacency_matrix_errors_name_the_physical_line() {
+        let error = |csv| {
+            Graph::parse_adjacency_matrix(csv)
+                .err()
+                .unwrap()
+                .to_string()
+        };
+        assert_eq!(
+            error(",\"a\nb\",c\n\"a\nb\",,1\nd,,\n"),
+            "Expected the row of \"c\" but found \"d\" on line 5"
+        );
+        assert_eq!(
+            error(",a\na,NaN\n"),
+            "Expected a finite weight but found \"NaN\" on line 2"
+        );
+    }
+}
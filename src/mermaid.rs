@@ -0,0 +1,511 @@
+use crate::core::{Attributes, Graph};
+use crate::error::{ParseError, Span};
+use std::collections::HashSet;
+use std::fmt::{Display, Write};
+
+/// Delimiters of the node shapes, by the value of the `shape` attribute.
+///
+/// Longer delimiters come first so that they are matched before their prefixes.
+static SHAPES: [(&str, &str, &str); 8] = [
+    ("stadium", "([", "])"),
+    ("subroutine", "[[", "]]"),
+    ("cylinder", "[(", ")]"),
+    ("circle", "((", "))"),
+    ("hexagon", "{{", "}}"),
+    ("rect", "[", "]"),
+    ("round", "(", ")"),
+    ("diamond", "{", "}"),
+];
+
+static DIRECTIONS: [&str; 5] = ["TB", "TD", "BT", "RL", "LR"];
+
+static KEYWORDS: [&str; 10] = [
+    "class",
+    "classDef",
+    "click",
+    "direction",
+    "end",
+    "flowchart",
+    "graph",
+    "linkStyle",
+    "style",
+    "subgraph",
+];
+
+fn is_id(text: &str) -> bool {
+    !text.is_empty()
+        && text.chars().all(|c| c.is_alphanumeric() || c == '_')
+        && !KEYWORDS.contains(&text)
+}
+
+fn escaped(text: &str) -> String {
+    text.replace('"', "#quot;")
+}
+
+fn unescaped(text: &str) -> String {
+    text.replace("#quot;", "\"")
+}
+
+/// One line of a flowchart, holding any number of statements separated by `;`.
+struct Line<'a> {
+    /// The whole flowchart, which `text` is a slice of.
+    source: &'a str,
+    text: &'a str,
+}
+
+impl<'a> Line<'a> {
+    /// Return an error that `expected` is missing at the start of the remaining text.
+    fn expected(&self, expected: &str) -> ParseError {
+        let next = self.text.chars().next().map_or(0, char::len_utf8);
+        ParseError::Expected {
+            expected: expected.to_string(),
+            found: None,
+            span: Span::of(self.source, &self.text[..next]),
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        self.text = self.text.trim_start();
+    }
+
+    /// Return whether the current statement has ended, consuming the `;` that ends it.
+    fn end_of_statement(&mut self) -> bool {
+        self.skip_whitespace();
+        if let Some(rest) = self.text.strip_prefix(';') {
+            self.text = rest;
+            true
+        } else {
+            self.text.is_empty()
+        }
+    }
+
+    fn take_while(&mut self, f: impl Fn(char) -> bool) -> &'a str {
+        let end = self.text.find(|c| !f(c)).unwrap_or(self.text.len());
+        let (taken, rest) = self.text.split_at(end);
+        self.text = rest;
+        taken
+    }
+
+    /// Return the text up to `close`, or up to a closing quote if the text starts with one.
+    fn text_until(&mut self, close: &str) -> Result<String, ParseError> {
+        let text = if let Some(rest) = self.text.strip_prefix('"') {
+            let end = match rest.find('"') {
+                Some(end) => end,
+                None => {
+                    return Err(ParseError::Unterminated {
+                        what: "string",
+                        span: Span::of(self.source, &self.text[..1]),
+                    })
+                }
+            };
+            self.text = &rest[end + 1..];
+            rest[..end].to_string()
+        } else {
+            let end = match self.text.find(close) {
+                Some(end) => end,
+                None => return Err(self.expected(&format!("{close:?}"))),
+            };
+            let text = self.text[..end].trim().to_string();
+            self.text = &self.text[end..];
+            text
+        };
+        match self.text.strip_prefix(close) {
+            Some(rest) => self.text = rest,
+            None => return Err(self.expected(&format!("{close:?}"))),
+        }
+        Ok(unescaped(&text))
+    }
+
+    fn node(&mut self, graph: &mut Graph<String>) -> Result<String, ParseError> {
+        self.skip_whitespace();
+        let id = self.take_while(|c| c.is_alphanumeric() || c == '_');
+        if !is_id(id) {
+            return Err(ParseError::Expected {
+                expected: String::from("a node"),
+                found: None,
+                span: Span::of(self.source, id),
+            });
+        }
+        let id = id.to_string();
+        graph.add_node(id.clone());
+        if let Some((shape, open, close)) = SHAPES.iter().find(|s| self.text.starts_with(s.1)) {
+            self.text = &self.text[open.len()..];
+            let label = self.text_until(close)?;
+            let attrs = graph.node_attrs_mut(graph.id_of(&id).unwrap());
+            attrs.insert(String::from("label"), label);
+            if *shape != "rect" {
+                attrs.insert(String::from("shape"), shape.to_string());
+            }
+        }
+        if let Some(rest) = self.text.strip_prefix(":::") {
+            self.text = rest;
+            self.take_while(|c| c.is_alphanumeric() || c == '_' || c == '-');
+        }
+        Ok(id)
+    }
+
+    /// Return the nodes of a group like `a & b`.
+    fn nodes(&mut self, graph: &mut Graph<String>) -> Result<Vec<String>, ParseError> {
+        let mut nodes = vec![self.node(graph)?];
+        loop {
+            self.skip_whitespace();
+            match self.text.strip_prefix('&') {
+                Some(rest) => self.text = rest,
+                None => return Ok(nodes),
+            }
+            nodes.push(self.node(graph)?);
+        }
+    }
+
+    /// Return the attributes of a link like `-->`, `-.->|label|` or `== label ==>`.
+    fn link(&mut self) -> Result<Attributes, ParseError> {
+        self.skip_whitespace();
+        let mut token = self.take_while(|c| c == '-' || c == '.' || c == '=');
+        let mut label = None;
+        let opening = ["--", "==", "-."];
+        if opening.contains(&token) && self.text.starts_with(char::is_whitespace) {
+            let closings: &[&str] = match token {
+                "--" => &["-->", "---"],
+                "==" => &["==>", "==="],
+                _ => &[".->", ".-"],
+            };
+            let (end, closing) = match closings
+                .iter()
+                .filter_map(|c| self.text.find(c).map(|i| (i, *c)))
+                .min()
+            {
+                Some(found) => found,
+                None => {
+                    return Err(ParseError::Unterminated {
+                        what: "link text",
+                        span: Span::of(self.source, token),
+                    })
+                }
+            };
+            label = Some(unescaped(self.text[..end].trim()));
+            self.text = &self.text[end + closing.len()..];
+            self.take_while(|c| c == '-' || c == '.' || c == '=');
+            token = closing;
+        }
+        if token.is_empty() && !self.text.starts_with('>') {
+            return Err(self.expected("a link"));
+        }
+        let arrow = self.text.starts_with('>') || token.ends_with('>');
+        if let Some(rest) = self.text.strip_prefix('>') {
+            self.text = rest;
+        }
+        let body = token.trim_end_matches('>');
+        let solid = body.chars().all(|c| c == '-');
+        let thick = body.chars().all(|c| c == '=');
+        let dotted = body.trim_matches('-').chars().all(|c| c == '.');
+        let length = body.len() + usize::from(arrow);
+        if label.is_none() && !(length >= 3 && (solid || thick || dotted)) {
+            return Err(ParseError::Unsupported {
+                what: format!("link {token:?}"),
+                span: Span::of(self.source, token),
+            });
+        }
+
+        self.skip_whitespace();
+        if let Some(rest) = self.text.strip_prefix('|') {
+            self.text = rest;
+            label = Some(self.text_until("|")?);
+        }
+        let mut attrs = Attributes::new();
+        if let Some(label) = label {
+            attrs.insert(String::from("label"), label);
+        }
+        if !solid && !thick {
+            attrs.insert(String::from("style"), String::from("dotted"));
+        } else if thick {
+            attrs.insert(String::from("style"), String::from("bold"));
+        }
+        if !arrow {
+            attrs.insert(String::from("arrowhead"), String::from("none"));
+        }
+        Ok(attrs)
+    }
+
+    fn statement(&mut self, graph: &mut Graph<String>) -> Result<(), ParseError> {
+        let mut tails = self.nodes(graph)?;
+        while !self.end_of_statement() {
+            let attrs = self.link()?;
+            let heads = self.nodes(graph)?;
+            for tail in tails.iter() {
+                for head in heads.iter() {
+                    graph.add_edge(tail.clone(), head.clone());
+                    let (t, h) = (graph.id_of(tail).unwrap(), graph.id_of(head).unwrap());
+                    graph.edge_attrs_mut(t, h).unwrap().extend(attrs.clone());
+                }
+            }
+            tails = heads;
+        }
+        Ok(())
+    }
+}
+
+impl Graph<String> {
+    /// Parse a Mermaid `flowchart` or `graph`, in the order that the nodes first appear.
+    ///
+    /// Labels go in the `label` attribute of nodes and edges. Nodes that are not rectangles get a
+    /// `shape` attribute, dotted and thick links a `style` attribute, and links without an arrow
+    /// `arrowhead=none`. Subgraphs are flattened.
+    pub fn parse_mermaid(s: &str) -> Result<Self, ParseError> {
+        let mut graph = Self::new();
+        let mut header = false;
+        let mut subgraphs = Vec::new();
+        for text in s.lines() {
+            let mut line = Line {
+                source: s,
+                text: text.trim(),
+            };
+            if line.text.is_empty() || line.text.starts_with("%%") {
+                continue;
+            }
+            let keyword = line.text.split_whitespace().next().unwrap_or_default();
+            let keyword = keyword.trim_end_matches(';');
+            if !header {
+                if keyword != "flowchart" && keyword != "graph" {
+                    return Err(line.expected("flowchart or graph"));
+                }
+                header = true;
+                line.text = &line.text[keyword.len()..];
+                line.skip_whitespace();
+                let direction = line.take_while(|c| c.is_ascii_alphabetic());
+                if !direction.is_empty() && !DIRECTIONS.contains(&direction) {
+                    return Err(ParseError::Expected {
+                        expected: String::from("a direction"),
+                        found: Some(format!("{direction:?}")),
+                        span: Span::of(s, direction),
+                    });
+                }
+                line.end_of_statement();
+                while !line.end_of_statement() {
+                    line.statement(&mut graph)?;
+                }
+                continue;
+            }
+            match keyword {
+                "subgraph" => subgraphs.push(keyword),
+                "end" if subgraphs.is_empty() => {
+                    return Err(ParseError::Expected {
+                        expected: String::from("a statement"),
+                        found: Some(String::from("'end'")),
+                        span: Span::of(s, keyword),
+                    })
+                }
+                "end" => {
+                    subgraphs.pop();
+                }
+                "class" | "classDef" | "click" | "direction" | "linkStyle" | "style" => {}
+                _ => {
+                    while !line.end_of_statement() {
+                        line.statement(&mut graph)?;
+                    }
+                }
+            }
+        }
+        if !header {
+            return Err(ParseError::Expected {
+                expected: String::from("flowchart or graph"),
+                found: None,
+                span: Span::new(s, s.len(), s.len()),
+            });
+        }
+        if let Some(subgraph) = subgraphs.pop() {
+            return Err(ParseError::Unterminated {
+                what: "subgraph",
+                span: Span::of(s, subgraph),
+            });
+        }
+        Ok(graph)
+    }
+}
+
+impl<T> Graph<T>
+where
+    T: Display,
+{
+    /// Return the graph as a Mermaid flowchart, such that [`Graph::parse_mermaid`] gives it back.
+    ///
+    /// Nodes that are not valid Mermaid ids are given new ids and labelled with their text. Edges of
+    /// undirected graphs have no arrowheads.
+    pub fn to_mermaid(&self) -> String {
+        let texts: Vec<String> = self
+            .node_ids()
+            .map(|id| self.node(id).to_string())
+            .collect();
+        let taken: HashSet<&str> = texts
+            .iter()
+            .map(String::as_str)
+            .filter(|t| is_id(t))
+            .collect();
+        let ids: Vec<String> = texts
+            .iter()
+            .enumerate()
+            .map(|(i, text)| {
+                if is_id(text) {
+                    return text.clone();
+                }
+                let mut id = format!("n{i}");
+                while taken.contains(id.as_str()) {
+                    id.push('_');
+                }
+                id
+            })
+            .collect();
+
+        let mut mermaid = String::from("flowchart TD\n");
+        for id in self.node_ids() {
+            let i = id.index();
+            let attrs = self.node_attrs(id);
+            let label = match attrs.get("label") {
+                Some(label) => Some(label),
+                None if ids[i] != texts[i] => Some(&texts[i]),
+                None => None,
+            };
+            let shape = attrs
+                .get("shape")
+                .and_then(|s| SHAPES.iter().find(|(n, ..)| n == s));
+            match (label, shape) {
+                (None, None) => writeln!(mermaid, "    {}", ids[i]).unwrap(),
+                (label, shape) => {
+                    let (_, open, close) = shape.unwrap_or(&SHAPES[5]);
+                    let label = escaped(label.unwrap_or(&texts[i]));
+                    writeln!(mermaid, "    {}{open}\"{label}\"{close}", ids[i]).unwrap()
+                }
+            }
+        }
+        for (tail, head) in self.edges() {
+            let attrs = self.edge_attrs(tail, head).unwrap();
+            let arrow =
+                self.is_directed() && attrs.get("arrowhead").map(String::as_str) != Some("none");
+            let link = match (attrs.get("style").map(String::as_str), arrow) {
+                (Some("dotted"), true) => "-.->",
+                (Some("dotted"), false) => "-.-",
+                (Some("bold"), true) => "==>",
+                (Some("bold"), false) => "===",
+                (_, true) => "-->",
+                (_, false) => "---",
+            };
+            let label = match attrs.get("label") {
+                Some(label) => format!("|\"{}\"|", escaped(label)),
+                None => String::new(),
+            };
+            writeln!(
+                mermaid,
+                "    {} {link}{label} {}",
+                ids[tail.index()],
+                ids[head.index()]
+            )
+            .unwrap();
+        }
+        mermaid
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Graph;
+
+    static FLOWCHART: &str = "\
+flowchart LR
+    %% Comments are ignored
+    start([Start]) --> check{Is it?}
+    check -->|Yes| ok[\"All #quot;good#quot;\"] & retry(Retry)
+    check -- No --- stop((Stop)); retry -.-> check
+    subgraph cleanup [Clean up]
+        direction TB
+        ok ==> stop
+    end
+    style stop fill:#f9f
+";
+
+    fn edge(graph: &Graph<String>, tail: &str, head: &str) -> Vec<(String, String)> {
+        let (t, h) = (graph.id_of(&tail.into()), graph.id_of(&head.into()));
+        graph
+            .edge_attrs(t.unwrap(), h.unwrap())
+            .unwrap()
+            .iter()
+            .map(|(k, v)| (k.clone(), v.clone()))
+            .collect()
+    }
+
+    #[test]
+    fn mermaid_is_parsed_in_order_of_appearance() {
+        let graph = Graph::parse_mermaid(FLOWCHART).unwrap();
+        let nodes: Vec<&str> = graph.nodes().into_iter().map(String::as_str).collect();
+        assert_eq!(nodes, vec!["start", "check", "ok", "retry", "stop"]);
+        assert_eq!(graph.edges().count(), 6);
+        let check = graph.id_of(&"check".into()).unwrap();
+        assert_eq!(graph.node_attrs(check)["label"], "Is it?");
+        assert_eq!(graph.node_attrs(check)["shape"], "diamond");
+        let ok = graph.id_of(&"ok".into()).unwrap();
+        assert_eq!(graph.node_attrs(ok)["label"], "All \"good\"");
+        assert_eq!(
+            edge(&graph, "check", "retry"),
+            vec![("label".into(), "Yes".into())]
+        );
+        assert_eq!(
+            edge(&graph, "check", "stop"),
+            vec![
+                ("arrowhead".into(), "none".into()),
+                ("label".into(), "No".into())
+            ]
+        );
+        assert_eq!(edge(&graph, "retry", "check")[0].1, "dotted");
+        assert_eq!(edge(&graph, "ok", "stop")[0].1, "bold");
+    }
+
+    #[test]
+    fn mermaid_round_trips() {
+        let graph = Graph::parse_mermaid(FLOWCHART).unwrap();
+        let mermaid = graph.to_mermaid();
+        let parsed = Graph::parse_mermaid(&mermaid).unwrap();
+        assert_eq!(parsed.nodes(), graph.nodes());
+        for (tail, head) in graph.edges() {
+            assert_eq!(parsed.edge_attrs(tail, head), graph.edge_attrs(tail, head));
+        }
+        for id in graph.node_ids() {
+            assert_eq!(parsed.node_attrs(id), graph.node_attrs(id));
+        }
+        assert_eq!(mermaid, parsed.to_mermaid());
+    }
+
+    #[test]
+    fn mermaid_gives_new_ids_to_other_text() {
+        let graph: Graph<&str> = vec![("a b", "n0"), ("n0", "end")].into_iter().collect();
+        assert_eq!(
+            graph.to_mermaid(),
+            "flowchart TD\n    n0_[\"a b\"]\n    n0\n    n2[\"end\"]\n    n0_ --> n0\n    n0 --> n2\n"
+        );
+    }
+
+    #[test]
+    fn mermaid_reads_statements_after_the_header() {
+        let graph = Graph::parse_mermaid("flowchart TD; A-->B\n  B --> C").unwrap();
+        assert_eq!(graph.nodes(), vec!["A", "B", "C"]);
+        assert_eq!(graph.edges().count(), 2);
+        let error = Graph::parse_mermaid("graph A-->B").err().unwrap();
+        assert_eq!(
+            error.to_string(),
+            "Expected a direction but found \"A\" on line 1"
+        );
+    }
+
+    #[test]
+    fn mermaid_reports_the_line_of_errors() {
+        let error = Graph::parse_mermaid("graph TD\n  a --> b\n  a -> b\n")
+            .err()
+            .unwrap();
+        assert_eq!(error.to_string(), "Unsupported link \"-\" on line 3");
+        assert_eq!((error.span().column, error.span().width), (5, 1));
+        let error = Graph::parse_mermaid("graph TD\n  subgraph one\n  a --> b\n")
+            .err()
+            .unwrap();
+        assert_eq!(
+            error.to_string(),
+            "Unterminated subgraph starting on line 2"
+        );
+    }
+}
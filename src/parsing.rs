@@ -1,84 +1,433 @@
-use crate::core::Graph;
-use anyhow::bail;
-use dot_parser::ast::{EdgeStmt, NodeID, Stmt};
-use dot_parser::canonical::Edge;
-use itertools::Either;
+use crate::core::{Attributes, Graph, Subgraph};
+use crate::dot::{self, AttrKind, AttrList, DotGraph, Endpoint, NodeRef, Stmt};
+use crate::error::{ParseError, Span};
 use std::collections::{HashMap, HashSet};
-use std::convert::TryFrom;
-
-/// Return nodes in the order that they should intuitively appear.
-fn nodes_in_order(
-    graph: dot_parser::ast::Graph<(&str, &str)>,
-) -> anyhow::Result<HashMap<String, usize>> {
-    let mut nodes = HashMap::new();
-    let mut add_node = |node: NodeID| {
-        if node.port.is_some() {
-            bail!("Node has port")
-        }
-        if !nodes.contains_key(&node.id) {
-            nodes.insert(node.id, nodes.len());
-        }
-        Ok(())
-    };
-
-    let mut deferred = Vec::new();
-    for stmt in graph.stmts {
-        match stmt {
-            Stmt::NodeStmt(stmt) => add_node(stmt.node)?,
-            Stmt::EdgeStmt(stmt) => {
-                for EdgeStmt { from, next, .. } in stmt.flatten() {
-                    match from {
-                        Either::Left(node) => add_node(node)?,
-                        Either::Right(_) => bail!("Unsupported edge from Subgraph"),
-                    };
-
-                    // `.flatten()` makes this impossible
-                    debug_assert!(next.next.is_none());
-                    match next.to {
-                        Either::Left(node) => deferred.push(node),
-                        Either::Right(_) => bail!("Unsupported edge to Subgraph"),
+
+fn push_new(names: &mut Vec<String>, name: &str) {
+    if !names.iter().any(|n| n == name) {
+        names.push(name.to_string());
+    }
+}
+
+fn attributes(list: &AttrList) -> Attributes {
+    list.iter()
+        .map(|(name, value)| (name.clone(), value.clone()))
+        .collect()
+}
+
+/// A named subgraph, with its members by name.
+struct Group {
+    name: String,
+    parent: Option<usize>,
+    members: Vec<String>,
+    attrs: Attributes,
+}
+
+/// Nodes, edges and named subgraphs of a DOT graph, by name.
+#[derive(Default)]
+struct Collected {
+    directed: bool,
+    /// Nodes in the order that they should intuitively appear.
+    nodes: HashMap<String, usize>,
+    deferred: Vec<String>,
+    edges: Vec<(String, String)>,
+    /// Named subgraphs in the order that they were opened.
+    groups: Vec<Group>,
+    /// The subgraphs that enclose the current statement, with `None` for anonymous ones.
+    scopes: Vec<Option<usize>>,
+    attrs: Attributes,
+    node_attrs: HashMap<String, Attributes>,
+    edge_attrs: HashMap<(String, String), Attributes>,
+    /// Attributes given by `node [...]` to nodes that are mentioned for the first time.
+    node_defaults: Attributes,
+    /// Attributes given by `edge [...]` to the edges of later statements.
+    edge_defaults: Attributes,
+}
+
+impl Collected {
+    fn add_node(&mut self, name: &str) {
+        if !self.nodes.contains_key(name) {
+            self.nodes.insert(name.to_string(), self.nodes.len());
+        }
+    }
+
+    /// Add `node` now, or after every statement if `defer`, and return its name.
+    fn node(&mut self, node: &NodeRef, defer: bool) -> String {
+        let name = &node.id;
+        if !self.node_attrs.contains_key(name) {
+            self.node_attrs
+                .insert(name.clone(), self.node_defaults.clone());
+        }
+        if defer {
+            self.deferred.push(name.clone());
+        } else {
+            self.add_node(name);
+        }
+        name.clone()
+    }
+
+    /// Add the edge from `from` to `to`, or merge `attrs` into it if it already exists.
+    ///
+    /// In an undirected graph, an edge in the opposite direction is the same edge.
+    fn edge(&mut self, from: &str, to: &str, mut attrs: Attributes) {
+        let reverse = (to.to_string(), from.to_string());
+        let edge = (from.to_string(), to.to_string());
+        if !self.directed && !self.edge_attrs.contains_key(&edge) {
+            if let Some(existing) = self.edge_attrs.get_mut(&reverse) {
+                let tailport = attrs.remove("tailport");
+                if let Some(port) = attrs.remove("headport") {
+                    attrs.insert(String::from("tailport"), port);
+                }
+                if let Some(port) = tailport {
+                    attrs.insert(String::from("headport"), port);
+                }
+                existing.extend(attrs);
+                return;
+            }
+        }
+        self.edge_attrs
+            .entry(edge.clone())
+            .or_default()
+            .extend(attrs);
+        self.edges.push(edge);
+    }
+
+    /// Return the attributes that `graph [...]` and `ID = ID` set in the current scope.
+    ///
+    /// Those of anonymous subgraphs are not kept.
+    fn graph_attrs(&mut self) -> Option<&mut Attributes> {
+        match self.scopes.last() {
+            None => Some(&mut self.attrs),
+            Some(Some(i)) => Some(&mut self.groups[*i].attrs),
+            Some(None) => None,
+        }
+    }
+
+    /// Collect `stmts` and return the names of the nodes in them.
+    fn stmts(&mut self, stmts: &[Stmt], defer: bool) -> Vec<String> {
+        let mut members = Vec::new();
+        for stmt in stmts {
+            match stmt {
+                Stmt::Node(node, list) => {
+                    let name = self.node(node, defer);
+                    self.node_attrs
+                        .get_mut(&name)
+                        .unwrap()
+                        .extend(attributes(list));
+                    push_new(&mut members, &name);
+                }
+                Stmt::Edge(endpoints, list) => {
+                    let mut ends = Vec::new();
+                    for (i, endpoint) in endpoints.iter().enumerate() {
+                        // Heads come after tails, as they would in a topological order.
+                        let defer = defer || i + 1 == endpoints.len();
+                        let (names, port) = match endpoint {
+                            Endpoint::Node(node) => {
+                                (vec![self.node(node, defer)], node.port.join(":"))
+                            }
+                            Endpoint::Subgraph(subgraph) => {
+                                (self.subgraph(subgraph, defer), String::new())
+                            }
+                        };
+                        for name in names.iter() {
+                            push_new(&mut members, name);
+                        }
+                        ends.push((names, port));
+                    }
+                    for pair in ends.windows(2) {
+                        let mut attrs = self.edge_defaults.clone();
+                        attrs.extend(attributes(list));
+                        for (name, port) in
+                            [("tailport", &pair[0].1), ("headport", &pair[1].1)].iter()
+                        {
+                            if !port.is_empty() {
+                                attrs.insert(name.to_string(), port.to_string());
+                            }
+                        }
+                        for from in pair[0].0.iter() {
+                            for to in pair[1].0.iter() {
+                                self.edge(from, to, attrs.clone());
+                            }
+                        }
+                    }
+                }
+                Stmt::Attr(AttrKind::Graph, list) => {
+                    if let Some(attrs) = self.graph_attrs() {
+                        attrs.extend(attributes(list));
+                    }
+                }
+                Stmt::Attr(AttrKind::Node, list) => self.node_defaults.extend(attributes(list)),
+                Stmt::Attr(AttrKind::Edge, list) => self.edge_defaults.extend(attributes(list)),
+                Stmt::Assign(name, value) => {
+                    if let Some(attrs) = self.graph_attrs() {
+                        attrs.insert(name.clone(), value.clone());
+                    }
+                }
+                Stmt::Subgraph(subgraph) => {
+                    for name in self.subgraph(subgraph, defer) {
+                        push_new(&mut members, &name);
                     }
                 }
             }
-            Stmt::AttrStmt(_) => { /* I don't think these should affect the order */ }
-            Stmt::IDEq(_, _) => bail!("Unsupported IDEq"),
-            Stmt::Subgraph(_) => bail!("Unsupported Subgraph"),
         }
+        members
     }
 
-    for node in deferred {
-        add_node(node)?;
+    /// Collect the statements of `subgraph`, recording its members if it is named.
+    ///
+    /// Defaults set inside the subgraph apply until its end.
+    fn subgraph(&mut self, subgraph: &dot::Subgraph, defer: bool) -> Vec<String> {
+        let group = match &subgraph.name {
+            // Like in Graphviz, subgraphs with the same name are the same subgraph.
+            Some(name) => match self.groups.iter().position(|g| g.name == *name) {
+                Some(i) => Some(i),
+                None => {
+                    self.groups.push(Group {
+                        name: name.clone(),
+                        parent: self.scopes.iter().rev().find_map(|s| *s),
+                        members: Vec::new(),
+                        attrs: Attributes::new(),
+                    });
+                    Some(self.groups.len() - 1)
+                }
+            },
+            None => None,
+        };
+        let defaults = (self.node_defaults.clone(), self.edge_defaults.clone());
+        self.scopes.push(group);
+        let members = self.stmts(&subgraph.stmts, defer);
+        self.scopes.pop();
+        (self.node_defaults, self.edge_defaults) = defaults;
+        if let Some(i) = group {
+            for name in members.iter() {
+                push_new(&mut self.groups[i].members, name);
+            }
+        }
+        members
     }
+}
 
-    Ok(nodes)
+/// Return a node label, which is a Graphviz escape string, as the text that it stands for.
+///
+/// Line breaks become single spaces, and are dropped at either end.
+fn expanded(label: &str, node: &str, graph: &str) -> String {
+    let mut text = String::new();
+    let mut line_break = false;
+    let mut chars = label.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        let part = match (c, chars.peek().map(|(_, e)| *e)) {
+            ('\\', Some(e)) if "nlrNG\\".contains(e) => {
+                chars.next();
+                match e {
+                    'N' => node,
+                    'G' => graph,
+                    '\\' => "\\",
+                    _ => {
+                        line_break = true;
+                        continue;
+                    }
+                }
+            }
+            _ => &label[i..i + c.len_utf8()],
+        };
+        if line_break && !text.is_empty() {
+            text.push(' ');
+        }
+        line_break = false;
+        text.push_str(part);
+    }
+    text
+}
+
+impl Graph<String> {
+    fn from_dot(graph: &DotGraph) -> Self {
+        let mut collected = Collected {
+            directed: graph.directed,
+            ..Collected::default()
+        };
+        collected.stmts(&graph.stmts, false);
+        for name in std::mem::take(&mut collected.deferred) {
+            collected.add_node(&name);
+        }
+
+        let nodes = &collected.nodes;
+        let mut heads: HashMap<usize, HashSet<usize>> = HashMap::new();
+        for (from, to) in collected.edges.iter() {
+            heads.entry(nodes[from]).or_default().insert(nodes[to]);
+        }
+        let mut result = Self::from_normalized(collected.nodes, heads);
+        let id = |graph: &Self, name: &String| graph.id_of(name).unwrap();
+        *result.attrs_mut() = collected.attrs;
+        let graph_name = graph.name.as_deref().unwrap_or_default();
+        for (name, mut attrs) in collected.node_attrs {
+            if let Some(label) = attrs.get_mut("label") {
+                *label = expanded(label, &name, graph_name);
+            }
+            *result.node_attrs_mut(id(&result, &name)) = attrs;
+        }
+        for ((from, to), attrs) in collected.edge_attrs {
+            let (t, h) = (id(&result, &from), id(&result, &to));
+            *result.edge_attrs_mut(t, h).unwrap() = attrs;
+        }
+        for group in collected.groups {
+            let nodes = group.members.iter().map(|m| id(&result, m)).collect();
+            result.subgraphs_mut().push(Subgraph {
+                name: group.name,
+                parent: group.parent,
+                nodes,
+                attrs: group.attrs,
+            });
+        }
+        result.set_directed(graph.directed);
+        result
+    }
+
+    /// Parse a document in the DOT language that holds one graph, with every form of ID read as
+    /// the name it stands for.
+    ///
+    /// Edges to or from a subgraph go to or from every node in it. Named subgraphs are kept in
+    /// [`Graph::subgraphs`]. Attributes are kept on the graph, nodes, edges and named subgraphs,
+    /// with defaults from `node [...]` and `edge [...]` applied. Ports of edges are kept in their
+    /// `tailport` and `headport` attributes. Node labels are read as Graphviz escape strings, with
+    /// `\N` and `\G` replaced by the names of the node and the graph and line breaks by spaces.
+    ///
+    /// Repeated edges are merged, as in a `strict` graph, since there is at most one edge from a
+    /// node to another. In an undirected graph, that includes edges in the opposite direction.
+    /// The `strict` keyword is therefore accepted and ignored, and [`Graph::to_dot`] never writes
+    /// it.
+    pub fn parse_dot(s: &str) -> Result<Self, ParseError> {
+        Ok(Self::from_dot(&dot::parse(s)?))
+    }
+
+    /// Parse every graph in a document in the DOT language, with its name if it has one.
+    pub fn parse_dot_all(s: &str) -> Result<Vec<(Option<String>, Self)>, ParseError> {
+        Ok(dot::parse_all(s)?
+            .iter()
+            .map(|graph| (graph.name.clone(), Self::from_dot(graph)))
+            .collect())
+    }
+}
+
+/// Return the lines that are neither blank nor `#` comments, trimmed.
+fn content_lines(s: &str) -> impl Iterator<Item = &str> {
+    s.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+}
+
+/// Return an error for `line` of `s` that says what was expected instead.
+fn expected(s: &str, line: &str, expected: &str) -> ParseError {
+    ParseError::Expected {
+        expected: expected.to_string(),
+        found: None,
+        span: Span::of(s, line),
+    }
 }
 
-fn heads(
-    nodes: &HashMap<String, usize>,
-    graph: dot_parser::canonical::Graph<(&str, &str)>,
-) -> HashMap<usize, HashSet<usize>> {
-    let mut heads: HashMap<usize, HashSet<usize>> = HashMap::new();
-    for Edge { from, to, .. } in graph.edges.set {
-        heads.entry(nodes[&from]).or_default().insert(nodes[&to]);
+/// Return the first word of `line` and the trimmed rest of it.
+fn first_word(line: &str) -> (&str, &str) {
+    match line.split_once(char::is_whitespace) {
+        Some((word, rest)) => (word, rest.trim()),
+        None => (line, ""),
     }
-    heads
 }
+
 impl Graph<String> {
-    pub fn parse_dot(s: &str) -> anyhow::Result<Self> {
-        let ast = dot_parser::ast::Graph::try_from(s)?;
-        let nodes = nodes_in_order(ast.clone())?;
-        let heads = heads(&nodes, dot_parser::canonical::Graph::from(ast));
-
-        if nodes.iter().any(|(n, _)| n.trim_matches('"') == n) {
-            bail!("Nodes must have double quoted id");
-        }
-
-        Ok(Self::from_normalized(
-            nodes
-                .into_iter()
-                .map(|(node, i)| (node.trim_matches('"').to_string(), i))
-                .collect(),
-            heads,
-        ))
+    /// Parse lines of `tail head`, or of a lone node, separated by spaces or tabs.
+    pub fn parse_edge_list(s: &str) -> Result<Self, ParseError> {
+        let mut heads = Vec::new();
+        for line in content_lines(s) {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            match fields.as_slice() {
+                [node] => heads.push((node.to_string(), Vec::new())),
+                [tail, head] => heads.push((tail.to_string(), vec![head.to_string()])),
+                _ => return Err(expected(s, line, "one or two nodes")),
+            }
+        }
+        Ok(Self::from_heads(heads))
+    }
+
+    /// Parse lines of `node: head head ...`, where a node may have no heads.
+    pub fn parse_adjacency_list(s: &str) -> Result<Self, ParseError> {
+        let mut heads = Vec::new();
+        for line in content_lines(s) {
+            let (tail, hs) = match line.split_once(':') {
+                Some((tail, hs)) => (tail.trim(), hs),
+                None => return Err(expected(s, line, "':'")),
+            };
+            if tail.is_empty() {
+                return Err(expected(s, line, "a node before ':'"));
+            }
+            heads.push((
+                tail.to_string(),
+                hs.split_whitespace().map(String::from).collect(),
+            ));
+        }
+        Ok(Self::from_heads(heads))
+    }
+
+    /// Parse the Trivial Graph Format, with labels in the `label` attribute of nodes and edges.
+    ///
+    /// Nodes are declared, one per line as an id and an optional label, before a line with only
+    /// `#`. Edges follow as two ids and an optional label. Other lines that start with `#` are
+    /// comments.
+    pub fn parse_tgf(s: &str) -> Result<Self, ParseError> {
+        let mut labels = Vec::new();
+        let mut edges = Vec::new();
+        let mut in_edges = false;
+        for line in s.lines() {
+            let line = line.trim();
+            if line == "#" && !in_edges {
+                in_edges = true;
+            } else if line.is_empty() || line.starts_with('#') {
+                continue;
+            } else if !in_edges {
+                let (node, label) = first_word(line);
+                if labels.iter().any(|(n, _)| *n == node) {
+                    return Err(ParseError::Duplicate {
+                        node: node.to_string(),
+                        span: Span::of(s, node),
+                    });
+                }
+                labels.push((node, label));
+            } else {
+                let (tail, rest) = first_word(line);
+                let (head, label) = first_word(rest);
+                if head.is_empty() {
+                    return Err(expected(s, line, "two nodes"));
+                }
+                for node in [tail, head] {
+                    if !labels.iter().any(|(n, _)| *n == node) {
+                        return Err(ParseError::Undeclared {
+                            node: node.to_string(),
+                            span: Span::of(s, node),
+                        });
+                    }
+                }
+                edges.push((tail, head, label));
+            }
+        }
+
+        let heads = labels
+            .iter()
+            .map(|(n, _)| (n.to_string(), Vec::new()))
+            .chain(
+                edges
+                    .iter()
+                    .map(|(t, h, _)| (t.to_string(), vec![h.to_string()])),
+            )
+            .collect();
+        let mut graph = Self::from_heads(heads);
+        let label = |text: &str| (String::from("label"), text.to_string());
+        for (node, text) in labels.into_iter().filter(|(_, l)| !l.is_empty()) {
+            let id = graph.id_of(&node.to_string()).unwrap();
+            graph.node_attrs_mut(id).extend([label(text)]);
+        }
+        for (tail, head, text) in edges.into_iter().filter(|(_, _, l)| !l.is_empty()) {
+            let t = graph.id_of(&tail.to_string()).unwrap();
+            let h = graph.id_of(&head.to_string()).unwrap();
+            graph.edge_attrs_mut(t, h).unwrap().extend([label(text)]);
+        }
+        Ok(graph)
     }
 }
@@ -0,0 +1,317 @@
+use crate::core::Graph;
+use crate::layout::Layout;
+use crate::svg::{DiagramOptions, Geometry};
+use ab_glyph::{point, Font, FontRef, PxScale, ScaleFont};
+use std::fmt::Display;
+use std::hash::Hash;
+use unicode_width::UnicodeWidthChar;
+
+/// Options for drawing PNG images with the embedded Hack font.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PngOptions {
+    /// Sizes in pixels, before scaling. Characters are as wide as in the font.
+    pub diagram: DiagramOptions,
+    /// Factor that every size, including the font size, is multiplied with.
+    pub scale: f64,
+    /// RGBA color of the lines, arrows and junctions.
+    pub lines: [u8; 4],
+    /// RGBA color of the labels.
+    pub text: [u8; 4],
+    /// RGBA color of the whole image before anything is drawn.
+    pub background: [u8; 4],
+}
+
+impl Default for PngOptions {
+    fn default() -> Self {
+        Self {
+            diagram: DiagramOptions::default(),
+            scale: 1.0,
+            lines: [0x44, 0x44, 0x44, 0xff],
+            text: [0x00, 0x00, 0x00, 0xff],
+            background: [0xff, 0xff, 0xff, 0xff],
+        }
+    }
+}
+
+impl<T> Graph<T>
+where
+    T: Eq + Hash + Display,
+{
+    /// Draw the same diagram as [`Graph::svg`] as a PNG image.
+    pub fn png(&self, order: &[&T], options: &PngOptions) -> anyhow::Result<Vec<u8>> {
+        let layout = self.diagram_layout(order, &options.diagram)?;
+        let pixmap = painted(&layout, options)?;
+        let mut data = Vec::new();
+        let mut encoder = png::Encoder::new(&mut data, pixmap.width as u32, pixmap.height as u32);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder.write_header()?.write_image_data(&pixmap.pixels)?;
+        Ok(data)
+    }
+}
+
+/// Samples per pixel along each axis when computing coverage.
+const SAMPLES: usize = 4;
+
+/// RGBA pixels without premultiplied alpha.
+struct Pixmap {
+    width: usize,
+    height: usize,
+    pixels: Vec<u8>,
+}
+
+impl Pixmap {
+    fn new(width: usize, height: usize, background: [u8; 4]) -> Self {
+        Self {
+            width,
+            height,
+            pixels: background.repeat(width * height),
+        }
+    }
+
+    fn blend(&mut self, x: i64, y: i64, color: [u8; 4], coverage: f32) {
+        if x < 0 || y < 0 || x as usize >= self.width || y as usize >= self.height {
+            return;
+        }
+        let i = 4 * (y as usize * self.width + x as usize);
+        let pixel = &mut self.pixels[i..i + 4];
+        let src_a = color[3] as f32 / 255.0 * coverage.min(1.0);
+        let dst_a = pixel[3] as f32 / 255.0;
+        let out_a = src_a + dst_a * (1.0 - src_a);
+        if out_a == 0.0 {
+            return;
+        }
+        for c in 0..3 {
+            let mixed = color[c] as f32 * src_a + pixel[c] as f32 * dst_a * (1.0 - src_a);
+            pixel[c] = (mixed / out_a).round() as u8;
+        }
+        pixel[3] = (out_a * 255.0).round() as u8;
+    }
+
+    /// Paint the part of the box from `(x0, y0)` to `(x1, y1)` where `inside` holds.
+    fn fill(
+        &mut self,
+        (x0, y0): (f64, f64),
+        (x1, y1): (f64, f64),
+        color: [u8; 4],
+        inside: impl Fn(f64, f64) -> bool,
+    ) {
+        let step = 1.0 / SAMPLES as f64;
+        for py in y0.floor() as i64..y1.ceil() as i64 {
+            for px in x0.floor() as i64..x1.ceil() as i64 {
+                let mut hits = 0;
+                for j in 0..SAMPLES {
+                    let y = py as f64 + (j as f64 + 0.5) * step;
+                    for i in 0..SAMPLES {
+                        let x = px as f64 + (i as f64 + 0.5) * step;
+                        if x0 <= x && x < x1 && y0 <= y && y < y1 && inside(x, y) {
+                            hits += 1;
+                        }
+                    }
+                }
+                if hits != 0 {
+                    let coverage = hits as f32 / (SAMPLES * SAMPLES) as f32;
+                    self.blend(px, py, color, coverage);
+                }
+            }
+        }
+    }
+
+    /// Paint the horizontal or vertical line from `a` to `b`, extended by `cap` at both ends.
+    fn line(&mut self, a: (f64, f64), b: (f64, f64), width: f64, cap: f64, color: [u8; 4]) {
+        let (x0, x1) = (a.0.min(b.0), a.0.max(b.0));
+        let (y0, y1) = (a.1.min(b.1), a.1.max(b.1));
+        let (dx, dy) = if y0 == y1 {
+            (cap, width / 2.0)
+        } else {
+            (width / 2.0, cap)
+        };
+        self.fill((x0 - dx, y0 - dy), (x1 + dx, y1 + dy), color, |_, _| true);
+    }
+
+    /// Paint the axis aligned `points`, with gaps if `dash` gives the length of dashes and gaps.
+    fn polyline(
+        &mut self,
+        points: &[(f64, f64)],
+        width: f64,
+        dash: Option<(f64, f64)>,
+        color: [u8; 4],
+    ) {
+        let mut offset = 0.0;
+        for pair in points.windows(2) {
+            let (a, b) = (pair[0], pair[1]);
+            let length = (b.0 - a.0).abs() + (b.1 - a.1).abs();
+            let at = |t: f64| {
+                if length == 0.0 {
+                    a
+                } else {
+                    (
+                        a.0 + (b.0 - a.0) * t / length,
+                        a.1 + (b.1 - a.1) * t / length,
+                    )
+                }
+            };
+            match dash {
+                None => self.line(a, b, width, width / 2.0, color),
+                Some((on, off)) => {
+                    let mut t = 0.0;
+                    while t < length {
+                        let phase = (offset + t) % (on + off);
+                        if phase < on {
+                            let end = length.min(t + on - phase);
+                            self.line(at(t), at(end), width, 0.0, color);
+                            t = end;
+                        } else {
+                            t += on + off - phase;
+                        }
+                    }
+                }
+            }
+            offset += length;
+        }
+    }
+
+    /// Paint an arrowhead with its tip at `tip`, pointing in the direction `(dx, dy)`.
+    fn arrow(&mut self, tip: (f64, f64), (dx, dy): (f64, f64), size: f64, color: [u8; 4]) {
+        let corners = [
+            tip,
+            (
+                tip.0 - dx * size - dy * size / 2.0,
+                tip.1 - dy * size + dx * size / 2.0,
+            ),
+            (
+                tip.0 - dx * size + dy * size / 2.0,
+                tip.1 - dy * size - dx * size / 2.0,
+            ),
+        ];
+        let min = corners
+            .iter()
+            .fold((f64::MAX, f64::MAX), |m, c| (m.0.min(c.0), m.1.min(c.1)));
+        let max = corners
+            .iter()
+            .fold((f64::MIN, f64::MIN), |m, c| (m.0.max(c.0), m.1.max(c.1)));
+        let side = |p: (f64, f64), q: (f64, f64), x: f64, y: f64| {
+            (q.0 - p.0) * (y - p.1) - (q.1 - p.1) * (x - p.0)
+        };
+        self.fill(min, max, color, |x, y| {
+            let sides = [
+                side(corners[0], corners[1], x, y),
+                side(corners[1], corners[2], x, y),
+                side(corners[2], corners[0], x, y),
+            ];
+            sides.iter().all(|s| *s >= 0.0) || sides.iter().all(|s| *s <= 0.0)
+        });
+    }
+}
+
+fn painted(layout: &Layout, options: &PngOptions) -> anyhow::Result<Pixmap> {
+    let font = FontRef::try_from_slice(epaint_default_fonts::HACK_REGULAR)?;
+    let units_per_em = match font.units_per_em() {
+        Some(units) => units,
+        None => anyhow::bail!("Embedded font has no units per em"),
+    };
+    let diagram = &options.diagram;
+    let font_size = diagram.font_size * options.scale;
+    let advance = font.h_advance_unscaled(font.glyph_id('0')) / units_per_em;
+    let geometry = Geometry::new(
+        layout,
+        diagram.gap,
+        font_size,
+        advance as f64,
+        diagram.line_height,
+        diagram.margin * options.scale,
+    );
+    let mut pixmap = Pixmap::new(
+        geometry.width().ceil() as usize,
+        geometry.height().ceil() as usize,
+        options.background,
+    );
+
+    let width = 1.5 * options.scale;
+    let dash = Some((4.0 * options.scale, 3.0 * options.scale));
+    let arrow = 6.0 * width;
+    for edge in layout.edges.iter() {
+        let dash = if edge.head < edge.tail { dash } else { None };
+        if edge.tail == edge.head {
+            let x = geometry.x(geometry.extents[edge.tail].label_end) - geometry.char_width / 2.0;
+            let y = geometry.y(edge.tail);
+            let r = geometry.char_width / 2.0;
+            let (inner, outer) = (r - width / 2.0, r + width / 2.0);
+            pixmap.fill(
+                (x - outer, y - outer),
+                (x + outer, y),
+                options.lines,
+                |px, py| {
+                    let d = ((px - x).powi(2) + (py - y).powi(2)).sqrt();
+                    inner <= d && d <= outer
+                },
+            );
+            pixmap.arrow(
+                (x + r, y + arrow / 4.0),
+                (0.0, 1.0),
+                arrow / 2.0,
+                options.lines,
+            );
+        } else {
+            let points = geometry.route(edge.tail, edge.head);
+            pixmap.polyline(&points, width, dash, options.lines);
+            pixmap.arrow(points[3], (1.0, 0.0), arrow, options.lines);
+        }
+    }
+
+    let radius = font_size / 7.0;
+    for junction in layout.junctions.iter() {
+        let (x, y) = (geometry.lane(junction.col), geometry.y(junction.row));
+        pixmap.fill(
+            (x - radius, y - radius),
+            (x + radius, y + radius),
+            options.lines,
+            |px, py| (px - x).powi(2) + (py - y).powi(2) <= radius * radius,
+        );
+    }
+
+    let scale = PxScale::from(font_size as f32 * font.height_unscaled() / units_per_em);
+    let scaled = font.as_scaled(scale);
+    let middle = (scaled.ascent() + scaled.descent()) / 2.0;
+    for (pos, node) in layout.nodes.iter().enumerate() {
+        let mut chars = geometry.extents[pos].label;
+        let baseline = geometry.y(pos) as f32 + middle;
+        for c in node.label.chars() {
+            let mut glyph = scaled.scaled_glyph(c);
+            glyph.position = point(geometry.x(chars) as f32, baseline);
+            if let Some(outline) = font.outline_glyph(glyph) {
+                let bounds = outline.px_bounds();
+                outline.draw(|x, y, coverage| {
+                    pixmap.blend(
+                        bounds.min.x as i64 + x as i64,
+                        bounds.min.y as i64 + y as i64,
+                        options.text,
+                        coverage,
+                    )
+                });
+            }
+            chars += c.width().unwrap_or(0);
+        }
+    }
+    Ok(pixmap)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::generators;
+    use crate::PngOptions;
+
+    #[test]
+    fn png_has_the_size_of_the_diagram() {
+        let graph = generators::cyclic();
+        let options = PngOptions {
+            scale: 2.0,
+            ..PngOptions::default()
+        };
+        let data = graph.png(&graph.nodes(), &options).unwrap();
+        let decoder = png::Decoder::new(data.as_slice());
+        let info = decoder.read_info().unwrap().info().clone();
+        assert_eq!(info.height, 436);
+        assert_eq!(data, graph.png(&graph.nodes(), &options).unwrap());
+    }
+}
@@ -1,275 +1,335 @@
-#![allow(clippy::if_same_then_else)]
 use crate::core::Graph;
-use anyhow::bail;
-use std::collections::HashMap;
-use std::fmt::{Debug, Display};
+use crate::layout::{Lane, Layout};
+use std::fmt::Display;
 use std::hash::Hash;
+use std::io::IsTerminal;
 
-type NormNode = usize;
-type NormTree = HashMap<NormNode, Vec<NormNode>>;
-
-fn normalized<T: Eq + Hash + Debug>(tree: &HashMap<T, Vec<T>>, order: &[T]) -> NormTree {
-    let mut lut = HashMap::new();
-    for (i, node) in order.iter().enumerate() {
-        lut.insert(node, i);
-    }
-    let mut result = HashMap::new();
-    for (k, old) in tree {
-        let mut new = Vec::with_capacity(old.len());
-        for v in old {
-            new.push(lut[v]);
-        }
-        result.insert(lut[k], new);
-    }
-    result
+/// Characters to draw the diagram with.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Glyphs {
+    pub padding: char,
+    pub vertical: char,
+    pub horizontal: char,
+    /// Where a vertical line passes a horizontal line without joining it.
+    pub crossing: char,
+    /// Where lines join, indexed by the directions that they leave in.
+    ///
+    /// The index is the sum of 1 for up, 2 for down, 4 for left and 8 for right.
+    pub junctions: [char; 16],
+    /// Placed right after the label of a node that has an edge to itself.
+    pub self_loop: char,
+    /// Placed right before the label of a node that edges arrive at.
+    pub arrow: char,
+    pub dashed_vertical: char,
+    pub dashed_horizontal: char,
+    pub dashed_crossing: char,
 }
 
-fn col_is_dsucc_of_row(tree: &NormTree, row: NormNode, col: NormNode) -> bool {
-    tree[&row].contains(&col)
-}
+impl Glyphs {
+    pub const ASCII: Glyphs = Glyphs {
+        padding: ' ',
+        vertical: '|',
+        horizontal: '-',
+        crossing: '|',
+        junctions: ['+'; 16],
+        self_loop: '@',
+        arrow: '>',
+        dashed_vertical: ':',
+        dashed_horizontal: '.',
+        dashed_crossing: ':',
+    };
 
-fn right_is_dsucc_of_row(tree: &NormTree, row: NormNode, col: NormNode) -> bool {
-    match tree[&row].iter().max() {
-        Some(last_ddesc) => col < *last_ddesc,
-        None => false,
-    }
-}
+    pub const UNICODE: Glyphs = Glyphs {
+        padding: ' ',
+        vertical: '│',
+        horizontal: '─',
+        crossing: '╂',
+        junctions: [
+            ' ', '│', '│', '│', '─', '╯', '╮', '┤', '─', '╰', '╭', '├', '─', '┴', '┬', '┼',
+        ],
+        self_loop: '↺',
+        arrow: '→',
+        dashed_vertical: '┆',
+        dashed_horizontal: '┄',
+        dashed_crossing: '╂',
+    };
 
-fn col_is_dsucc_of_above(tree: &NormTree, row: NormNode, col: NormNode) -> bool {
-    for row_above in 0..row {
-        if tree[&row_above].contains(&col) {
-            return true;
+    fn junction(&self, lane: &Lane) -> char {
+        let mut i = 0;
+        for (bit, direction) in [lane.up, lane.down, lane.left, lane.right]
+            .iter()
+            .enumerate()
+        {
+            if *direction {
+                i |= 1 << bit;
+            }
         }
+        self.junctions[i]
     }
-    false
 }
 
-fn right_is_dsucc_of_above(tree: &NormTree, row: NormNode, col: NormNode) -> bool {
-    for row_above in 0..row {
-        if right_is_dsucc_of_row(tree, row_above, col) {
-            return true;
-        }
-    }
-    false
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LineEnding {
+    Lf,
+    CrLf,
 }
 
-fn row_is_dpred_of_left(tree: &NormTree, row: NormNode, col: NormNode) -> bool {
-    for col_left in 0..col {
-        if tree[&row].contains(&col_left) {
-            return true;
+impl LineEnding {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LineEnding::Lf => "\n",
+            LineEnding::CrLf => "\r\n",
         }
     }
-    false
 }
 
-fn anywhere_is_dsucc_of_col(tree: &NormTree, _row: NormNode, col: NormNode) -> bool {
-    for dsuccs in tree.values() {
-        if dsuccs.contains(&col) {
-            return true;
-        }
-    }
-    false
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ColorMode {
+    Never,
+    Always,
+    /// Color only when stdout is a terminal and `NO_COLOR` is not set.
+    Auto,
 }
 
-fn below_is_dsucc_of_col(tree: &NormTree, row: NormNode, col: NormNode) -> bool {
-    let n = tree.len();
-    for row_below in row..n {
-        if tree[&row_below].contains(&col) {
-            return true;
+impl ColorMode {
+    pub fn enabled(&self) -> bool {
+        match self {
+            ColorMode::Never => false,
+            ColorMode::Always => true,
+            ColorMode::Auto => {
+                std::env::var_os("NO_COLOR").is_none() && std::io::stdout().is_terminal()
+            }
         }
     }
-    false
 }
 
-fn left_is_dsucc_of_row(tree: &NormTree, row: NormNode, col: NormNode) -> bool {
-    for left_col in 0..col {
-        if tree[&row].contains(&left_col) {
-            return true;
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RenderOptions {
+    pub glyphs: Glyphs,
+    /// Labels longer than this are truncated.
+    pub max_label_width: usize,
+    /// Text that truncated labels end with, counted towards `max_label_width`.
+    pub truncation_marker: String,
+    /// Width of the space between a lane and the label on either side of it.
+    pub gap: usize,
+    pub line_ending: LineEnding,
+    /// Whether to mark where edges arrive at their head.
+    pub arrows: bool,
+    /// Whether to draw the edges to earlier nodes with dashed lines.
+    pub dashed_back_edges: bool,
+    /// Whether to color the lines with ANSI escape codes.
+    ///
+    /// Every lane gets a color from the palette and every horizontal line gets the color of the
+    /// lane that it turns into next.
+    pub color: ColorMode,
+    /// SGR parameters, such as `"31"` or `"38;5;208"`, to cycle through.
+    pub palette: Vec<String>,
+}
+
+impl Default for RenderOptions {
+    fn default() -> Self {
+        Self {
+            glyphs: Glyphs::ASCII,
+            max_label_width: 50,
+            truncation_marker: String::new(),
+            gap: 1,
+            line_ending: LineEnding::Lf,
+            arrows: false,
+            dashed_back_edges: false,
+            color: ColorMode::Never,
+            palette: ["31", "32", "33", "34", "35", "36"]
+                .iter()
+                .map(|p| p.to_string())
+                .collect(),
         }
     }
-    false
 }
 
-static MAX_COL_WIDTH: usize = 50;
-
-static PADDINGS: &str = "                                                  ";
-static HORIZONTALS: &str = "--------------------------------------------------";
-
-static NOTHING: &str = "";
-static PADDING: &str = " ";
-static JUNCTION: &str = "+";
-static VERTICAL: &str = "|";
-static HORIZONTAL: &str = "-";
-
 impl<T> Graph<T>
 where
-    T: Eq + Hash + Display + Debug,
+    T: Eq + Hash + Display,
 {
     pub fn ascii(&self) -> anyhow::Result<String> {
         self.ascii_with_order(&self.nodes())
     }
 
     pub fn ascii_with_order(&self, order: &Vec<&T>) -> anyhow::Result<String> {
-        let tree = self
-            .heads()
-            .into_iter()
-            .map(|(k, v)| (k, v.into_iter().collect()))
-            .collect();
-        formatted(&tree, order)
+        self.render(order, &RenderOptions::default())
+    }
+
+    pub fn render(&self, order: &[&T], options: &RenderOptions) -> anyhow::Result<String> {
+        let layout =
+            self.truncated_layout(order, options.max_label_width, &options.truncation_marker)?;
+        Ok(formatted(&layout, options))
     }
 }
 
-fn formatted<T: Eq + Hash + Display + Debug>(
-    tree: &HashMap<T, Vec<T>>,
-    order: &Vec<T>,
-) -> anyhow::Result<String> {
-    let n = order.len();
+fn lane(glyphs: &Glyphs, lane: &Lane, dashed: bool) -> char {
+    let vertical = lane.up || lane.down;
+    let horizontal = lane.left || lane.right;
+    if lane.junction {
+        glyphs.junction(lane)
+    } else if vertical && horizontal && dashed {
+        glyphs.dashed_crossing
+    } else if vertical && horizontal {
+        glyphs.crossing
+    } else if vertical && dashed {
+        glyphs.dashed_vertical
+    } else if vertical {
+        glyphs.vertical
+    } else if horizontal && dashed {
+        glyphs.dashed_horizontal
+    } else if horizontal {
+        glyphs.horizontal
+    } else {
+        glyphs.padding
+    }
+}
 
-    let mut names: Vec<String> = Vec::with_capacity(n);
-    for node in order {
-        if tree[node].contains(node) {
-            bail!("Omitting self loop")
-        }
+fn line(glyphs: &Glyphs, horizontal: bool, dashed: bool) -> char {
+    if !horizontal {
+        glyphs.padding
+    } else if dashed {
+        glyphs.dashed_horizontal
+    } else {
+        glyphs.horizontal
+    }
+}
 
-        let name = format!("{}", node).chars().take(MAX_COL_WIDTH).collect();
-        names.push(name);
+/// Text that switches colors only when needed.
+struct Canvas<'a> {
+    text: String,
+    palette: &'a [String],
+    current: Option<usize>,
+}
+
+impl Canvas<'_> {
+    fn paint(&mut self, lane: Option<usize>) {
+        let color = match lane {
+            Some(lane) if !self.palette.is_empty() => Some(lane % self.palette.len()),
+            _ => None,
+        };
+        if color != self.current {
+            if self.current.is_some() {
+                self.text.push_str("\x1b[0m");
+            }
+            if let Some(color) = color {
+                self.text.push_str("\x1b[");
+                self.text.push_str(&self.palette[color]);
+                self.text.push('m');
+            }
+            self.current = color;
+        }
     }
-    let names = names;
 
-    let mut col_widths = Vec::with_capacity(n);
-    for name in names.iter() {
-        col_widths.push(name.len());
+    fn push(&mut self, lane: Option<usize>, glyph: char) {
+        self.paint(lane);
+        self.text.push(glyph);
     }
-    let col_widths = col_widths;
 
-    let tree = normalized(tree, order);
-    let norm = &tree;
-    let mut result = String::new();
-    for row in 0..n {
-        if row != 0 {
-            result.push('\n')
+    fn repeat(&mut self, lane: Option<usize>, glyph: char, width: usize) {
+        if width != 0 {
+            self.paint(lane);
+            self.text.extend(std::iter::repeat_n(glyph, width));
         }
-        for col in 0..n {
-            let col_width = col_widths[col];
+    }
 
-            let ll = if col == 0 && !anywhere_is_dsucc_of_col(norm, row, col) {
-                NOTHING
-            } else if col < row {
-                if col_is_dsucc_of_row(norm, row, col) {
-                    JUNCTION
-                } else if below_is_dsucc_of_col(norm, row, col) {
-                    VERTICAL
-                } else if row_is_dpred_of_left(norm, row, col) {
-                    HORIZONTAL
-                } else {
-                    PADDING
-                }
-            } else if col == row {
-                if anywhere_is_dsucc_of_col(norm, row, col) {
-                    JUNCTION
-                } else if left_is_dsucc_of_row(norm, row, col) {
-                    HORIZONTAL
-                } else if right_is_dsucc_of_above(norm, row, col) {
-                    PADDING
-                } else if col != 0 {
-                    PADDING
-                } else {
-                    NOTHING
-                }
-            } else if col_is_dsucc_of_row(norm, row, col) {
-                JUNCTION
-            } else if col_is_dsucc_of_above(norm, row, col) {
-                VERTICAL
-            } else if right_is_dsucc_of_row(norm, row, col) {
-                HORIZONTAL
-            } else if right_is_dsucc_of_above(norm, row, col) {
-                PADDING
-            } else {
-                NOTHING
-            };
+    fn push_str(&mut self, text: &str) {
+        self.paint(None);
+        self.text.push_str(text);
+    }
+}
 
-            let lr = if col == 0 && !anywhere_is_dsucc_of_col(norm, row, col) {
-                NOTHING
-            } else if col < row {
-                if col_is_dsucc_of_row(norm, row, col) {
-                    HORIZONTAL
-                } else if row_is_dpred_of_left(norm, row, col) {
-                    HORIZONTAL
-                } else if below_is_dsucc_of_col(norm, row, col) {
-                    PADDING
-                } else {
-                    PADDING
-                }
-            } else if col == row {
-                if anywhere_is_dsucc_of_col(norm, row, col) {
-                    HORIZONTAL
-                } else if left_is_dsucc_of_row(norm, row, col) {
-                    HORIZONTAL
-                } else if right_is_dsucc_of_above(norm, row, col) {
-                    PADDING
-                } else if col != 0 {
-                    PADDING
-                } else {
-                    NOTHING
-                }
-            } else if right_is_dsucc_of_row(norm, row, col) {
-                HORIZONTAL
-            } else if right_is_dsucc_of_above(norm, row, col) {
-                PADDING
-            } else {
-                NOTHING
-            };
+/// Return the next head after `col` in the given direction, counting `col` itself if `inclusive`.
+///
+/// This is the lane that a horizontal line at `col` turns into.
+fn turn(heads: &[usize], col: usize, rightwards: bool, inclusive: bool) -> Option<usize> {
+    if rightwards {
+        let i = heads.partition_point(|h| *h < col || (*h == col && !inclusive));
+        heads.get(i).copied()
+    } else {
+        let i = heads.partition_point(|h| *h < col || (*h == col && inclusive));
+        i.checked_sub(1).map(|i| heads[i])
+    }
+}
 
-            let cc = if col < row {
-                if col_is_dsucc_of_row(norm, row, col) {
-                    &HORIZONTALS[..col_width]
-                } else if row_is_dpred_of_left(norm, row, col) {
-                    &HORIZONTALS[..col_width]
-                } else if below_is_dsucc_of_col(norm, row, col) {
-                    &PADDINGS[..col_width]
-                } else {
-                    &PADDINGS[..col_width]
-                }
-            } else if col == row {
-                &names[col]
-            } else if right_is_dsucc_of_row(norm, row, col) {
-                &HORIZONTALS[..col_width]
-            } else if right_is_dsucc_of_above(norm, row, col) {
-                &PADDINGS[..col_width]
-            } else {
-                NOTHING
-            };
+fn formatted(layout: &Layout, options: &RenderOptions) -> String {
+    let glyphs = &options.glyphs;
+    let gap = options.gap;
+    let palette: &[String] = if options.color.enabled() {
+        &options.palette
+    } else {
+        &[]
+    };
+    let mut heads = vec![Vec::new(); layout.len()];
+    for edge in layout.edges.iter().filter(|e| e.tail != e.head) {
+        heads[edge.tail].push(edge.head);
+    }
 
-            let rr = if col < row {
-                if col_is_dsucc_of_row(norm, row, col) {
-                    HORIZONTAL
-                } else if row_is_dpred_of_left(norm, row, col) {
-                    HORIZONTAL
-                } else if below_is_dsucc_of_col(norm, row, col) {
-                    PADDING
+    let mut canvas = Canvas {
+        text: String::new(),
+        palette,
+        current: None,
+    };
+    for (row, cells) in layout.rows.iter().enumerate() {
+        if row != 0 {
+            canvas.push_str(options.line_ending.as_str())
+        }
+        let heads = &heads[row];
+        let end = cells.len() - 1;
+        for (col, cell) in cells.iter().enumerate() {
+            let node = &layout.nodes[col];
+            let width = node.width + if node.self_loop { 1 } else { 0 };
+            // Edges to earlier rows are the only ones that run below the diagonal
+            let dashed = options.dashed_back_edges && col < row;
+            if node.lane {
+                let dashed = options.dashed_back_edges && col <= row;
+                let l = &cell.lane;
+                let color = if l.junction || l.up || l.down {
+                    Some(col)
                 } else {
-                    PADDING
+                    turn(heads, col, row < col, false)
+                };
+                canvas.push(color, lane(glyphs, l, dashed));
+            }
+            if row < col && col == end {
+                continue;
+            }
+            if col == row {
+                if node.lane {
+                    let dashed = options.dashed_back_edges && !cell.lane.up;
+                    let glyph = line(glyphs, cell.before, dashed);
+                    let color = if cell.lane.junction {
+                        Some(col)
+                    } else {
+                        turn(heads, col, false, false)
+                    };
+                    if options.arrows && cell.lane.junction && gap != 0 {
+                        canvas.repeat(color, glyph, gap - 1);
+                        canvas.push(color, glyphs.arrow);
+                    } else {
+                        canvas.repeat(color, glyph, gap);
+                    }
                 }
-            } else if col == row {
-                if right_is_dsucc_of_row(norm, row, col) {
-                    HORIZONTAL
-                } else if right_is_dsucc_of_above(norm, row, col) {
-                    PADDING
-                } else {
-                    NOTHING
+                canvas.push_str(&node.label);
+                if node.self_loop {
+                    canvas.push(Some(col), glyphs.self_loop);
+                }
+                if col != end {
+                    let color = turn(heads, col, true, false);
+                    canvas.repeat(color, line(glyphs, cell.after, false), gap);
                 }
-            } else if right_is_dsucc_of_row(norm, row, col) {
-                HORIZONTAL
-            } else if right_is_dsucc_of_above(norm, row, col) {
-                PADDING
             } else {
-                NOTHING
-            };
-            result.push_str(ll);
-            result.push_str(lr);
-            result.push_str(cc);
-            result.push_str(rr);
+                let color = turn(heads, col, row < col, col < row);
+                let glyph = line(glyphs, cell.before, dashed);
+                if node.lane {
+                    canvas.repeat(color, glyph, gap);
+                }
+                canvas.repeat(color, glyph, width);
+                canvas.repeat(color, line(glyphs, cell.after, dashed), gap);
+            }
         }
     }
-    Ok(result)
+    canvas.paint(None);
+    canvas.text
 }
@@ -0,0 +1,171 @@
+use crate::core::{Attributes, Graph, Subgraph};
+use serde::de::Error;
+use serde::ser::SerializeStruct;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::collections::BTreeMap;
+use std::hash::Hash;
+
+/// Graphs are serialized as their nodes in order, followed by edges between the positions of nodes
+/// and by any attributes and subgraphs. Undirected graphs also have `"directed": false`.
+impl<T> Serialize for Graph<T>
+where
+    T: Serialize,
+{
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let nodes: Vec<&T> = self.node_ids().map(|id| self.node(id)).collect();
+        let edges: Vec<(usize, usize)> =
+            self.edges().map(|(t, h)| (t.index(), h.index())).collect();
+        let node_attrs: BTreeMap<usize, &Attributes> = self
+            .node_ids()
+            .map(|id| (id.index(), self.node_attrs(id)))
+            .filter(|(_, attrs)| !attrs.is_empty())
+            .collect();
+        let edge_attrs: Vec<(usize, usize, &Attributes)> = self
+            .edges()
+            .map(|(t, h)| (t.index(), h.index(), self.edge_attrs(t, h).unwrap()))
+            .filter(|(_, _, attrs)| !attrs.is_empty())
+            .collect();
+
+        let mut state = serializer.serialize_struct("Graph", 7)?;
+        state.serialize_field("nodes", &nodes)?;
+        state.serialize_field("edges", &edges)?;
+        if self.attrs().is_empty() {
+            state.skip_field("attrs")?;
+        } else {
+            state.serialize_field("attrs", self.attrs())?;
+        }
+        if node_attrs.is_empty() {
+            state.skip_field("node_attrs")?;
+        } else {
+            state.serialize_field("node_attrs", &node_attrs)?;
+        }
+        if edge_attrs.is_empty() {
+            state.skip_field("edge_attrs")?;
+        } else {
+            state.serialize_field("edge_attrs", &edge_attrs)?;
+        }
+        if self.is_directed() {
+            state.skip_field("directed")?;
+        } else {
+            state.serialize_field("directed", &false)?;
+        }
+        if self.subgraphs().is_empty() {
+            state.skip_field("subgraphs")?;
+        } else {
+            state.serialize_field("subgraphs", self.subgraphs())?;
+        }
+        state.end()
+    }
+}
+
+fn directed() -> bool {
+    true
+}
+
+#[derive(Deserialize)]
+#[serde(rename = "Graph", deny_unknown_fields)]
+struct Serialized<T> {
+    nodes: Vec<T>,
+    edges: Vec<(usize, usize)>,
+    #[serde(default)]
+    attrs: Attributes,
+    #[serde(default)]
+    node_attrs: BTreeMap<usize, Attributes>,
+    #[serde(default)]
+    edge_attrs: Vec<(usize, usize, Attributes)>,
+    #[serde(default = "directed")]
+    directed: bool,
+    #[serde(default)]
+    subgraphs: Vec<Subgraph>,
+}
+
+impl<'de, T> Deserialize<'de> for Graph<T>
+where
+    T: Clone + Eq + Hash + Deserialize<'de>,
+{
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let serialized = Serialized::<T>::deserialize(deserializer)?;
+        let mut graph = Self::new();
+        for node in serialized.nodes {
+            if !graph.add_node(node) {
+                return Err(D::Error::custom("duplicate node"));
+            }
+        }
+        let ids: Vec<_> = graph.node_ids().collect();
+        let id = |i: usize| match ids.get(i) {
+            Some(id) => Ok(*id),
+            None => Err(D::Error::custom(format!("node {i} does not exist"))),
+        };
+        for (t, h) in serialized.edges {
+            let (tail, head) = (graph.node(id(t)?).clone(), graph.node(id(h)?).clone());
+            graph.add_edge(tail, head);
+        }
+        *graph.attrs_mut() = serialized.attrs;
+        for (i, attrs) in serialized.node_attrs {
+            *graph.node_attrs_mut(id(i)?) = attrs;
+        }
+        for (t, h, attrs) in serialized.edge_attrs {
+            match graph.edge_attrs_mut(id(t)?, id(h)?) {
+                Some(edge) => *edge = attrs,
+                None => return Err(D::Error::custom(format!("edge ({t}, {h}) does not exist"))),
+            }
+        }
+        for (i, subgraph) in serialized.subgraphs.iter().enumerate() {
+            for node in subgraph.nodes.iter() {
+                id(node.index())?;
+            }
+            if subgraph.parent.is_some_and(|p| p >= i) {
+                return Err(D::Error::custom(format!(
+                    "subgraph {i} does not come after its parent"
+                )));
+            }
+        }
+        graph.set_directed(serialized.directed);
+        *graph.subgraphs_mut() = serialized.subgraphs;
+        Ok(graph)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{generators, Graph};
+
+    #[test]
+    fn graph_round_trips_through_json() {
+        let mut graph = generators::diagv();
+        let (a, v) = (graph.id_of(&'a').unwrap(), graph.id_of(&'v').unwrap());
+        graph.node_attrs_mut(v).insert("label".into(), "V".into());
+        graph
+            .edge_attrs_mut(a, v)
+            .unwrap()
+            .insert("style".into(), "bold".into());
+        let json = serde_json::to_string(&graph).unwrap();
+        assert_eq!(
+            json,
+            r#"{"nodes":["d","i","a","g","v"],"edges":[[0,2],[1,2],[1,3],[2,4],[3,4]],"node_attrs":{"4":{"label":"V"}},"edge_attrs":[[2,4,{"style":"bold"}]]}"#
+        );
+        let parsed: Graph<char> = serde_json::from_str(&json).unwrap();
+        assert_eq!(serde_json::to_string(&parsed).unwrap(), json);
+    }
+
+    #[test]
+    fn undirected_graph_with_subgraphs_round_trips_through_json() {
+        let graph = Graph::parse_dot("graph { subgraph s { a -- b } c }").unwrap();
+        let json = serde_json::to_string(&graph).unwrap();
+        assert_eq!(
+            json,
+            r#"{"nodes":["a","c","b"],"edges":[[0,2]],"directed":false,"subgraphs":[{"name":"s","parent":null,"nodes":[0,2]}]}"#
+        );
+        let parsed: Graph<String> = serde_json::from_str(&json).unwrap();
+        assert!(!parsed.is_directed());
+        assert_eq!(parsed.subgraphs(), graph.subgraphs());
+    }
+
+    #[test]
+    fn deserialization_rejects_missing_endpoints() {
+        let error = serde_json::from_str::<Graph<char>>(r#"{"nodes":["a"],"edges":[[0,1]]}"#)
+            .err()
+            .unwrap();
+        assert_eq!(error.to_string(), "node 1 does not exist");
+    }
+}
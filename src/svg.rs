@@ -0,0 +1,314 @@
+use crate::core::Graph;
+use crate::layout::{Extent, Layout};
+use std::fmt::{Display, Write};
+use std::hash::Hash;
+
+/// Sizes of a drawn diagram, shared by [`SvgOptions`], `PngOptions` and
+/// [`GraphvizOptions`](crate::GraphvizOptions).
+#[derive(Clone, Debug, PartialEq)]
+pub struct DiagramOptions {
+    /// In the unit of the backend, such as pixels or points.
+    pub font_size: f64,
+    /// Height of a row relative to the font size.
+    pub line_height: f64,
+    /// Labels longer than this, in characters, are truncated.
+    pub max_label_width: usize,
+    /// Text that truncated labels end with, counted towards `max_label_width`.
+    pub truncation_marker: String,
+    /// Width, in characters, of the space between a lane and the label on either side of it.
+    pub gap: usize,
+    /// Space around the diagram, in the unit of `font_size`.
+    pub margin: f64,
+}
+
+impl Default for DiagramOptions {
+    fn default() -> Self {
+        Self {
+            font_size: 14.0,
+            line_height: 1.6,
+            max_label_width: 50,
+            truncation_marker: String::new(),
+            gap: 1,
+            margin: 8.0,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct SvgOptions {
+    /// Should be monospace; labels are stretched to the width that a monospace font would give.
+    pub font_family: String,
+    /// Width of a character relative to the font size.
+    pub advance: f64,
+    /// Sizes in pixels.
+    pub diagram: DiagramOptions,
+}
+
+impl Default for SvgOptions {
+    fn default() -> Self {
+        Self {
+            font_family: String::from("monospace"),
+            advance: 0.6,
+            diagram: DiagramOptions::default(),
+        }
+    }
+}
+
+static STYLE: &str = "\
+.line{fill:none;stroke:#444;stroke-width:1.5}\
+.back .line{stroke-dasharray:4 3}\
+.junction{fill:#444}\
+.node text{fill:#000;dominant-baseline:central}\
+#diagv-arrow{fill:#444}\
+#diagv-arrow path{fill:context-stroke}";
+
+impl<T> Graph<T>
+where
+    T: Eq + Hash + Display,
+{
+    /// Draw the same diagram as [`Graph::render`] as a standalone SVG document.
+    pub fn svg(&self, order: &[&T], options: &SvgOptions) -> anyhow::Result<String> {
+        let layout = self.diagram_layout(order, &options.diagram)?;
+        Ok(drawn(&layout, options))
+    }
+
+    /// Return the layout of [`Graph::layout`] with its labels truncated as `options` say.
+    pub(crate) fn diagram_layout(
+        &self,
+        order: &[&T],
+        options: &DiagramOptions,
+    ) -> anyhow::Result<Layout> {
+        self.truncated_layout(order, options.max_label_width, &options.truncation_marker)
+    }
+}
+
+/// Return `x` rounded to two decimals so that the output does not depend on float noise.
+pub(crate) fn num(x: f64) -> f64 {
+    let x = (x * 100.0).round() / 100.0;
+    if x == 0.0 {
+        0.0
+    } else {
+        x
+    }
+}
+
+pub(crate) fn escaped(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => result.push_str("&amp;"),
+            '<' => result.push_str("&lt;"),
+            '>' => result.push_str("&gt;"),
+            '"' => result.push_str("&quot;"),
+            '\'' => result.push_str("&#39;"),
+            c => result.push(c),
+        }
+    }
+    result
+}
+
+/// Pixel coordinates of the parts of the diagram.
+pub(crate) struct Geometry {
+    pub(crate) extents: Vec<Extent>,
+    pub(crate) char_width: f64,
+    pub(crate) row_height: f64,
+    pub(crate) margin: f64,
+}
+
+impl Geometry {
+    pub(crate) fn new(
+        layout: &Layout,
+        gap: usize,
+        font_size: f64,
+        advance: f64,
+        line_height: f64,
+        margin: f64,
+    ) -> Self {
+        Self {
+            extents: layout.extents(gap),
+            char_width: font_size * advance,
+            row_height: font_size * line_height,
+            margin,
+        }
+    }
+
+    pub(crate) fn x(&self, chars: usize) -> f64 {
+        num(self.margin + chars as f64 * self.char_width)
+    }
+
+    /// Return the middle of the lane of `col`.
+    pub(crate) fn lane(&self, col: usize) -> f64 {
+        num(self.x(self.extents[col].lane) + self.char_width / 2.0)
+    }
+
+    /// Return the middle of `row`.
+    pub(crate) fn y(&self, row: usize) -> f64 {
+        num(self.margin + (row as f64 + 0.5) * self.row_height)
+    }
+
+    pub(crate) fn width(&self) -> f64 {
+        let chars = self.extents.last().map_or(0, |e| e.end);
+        num(2.0 * self.margin + chars as f64 * self.char_width)
+    }
+
+    pub(crate) fn height(&self) -> f64 {
+        num(2.0 * self.margin + self.extents.len() as f64 * self.row_height)
+    }
+
+    /// Return the corners of the path of the edge from `tail` to `head`.
+    pub(crate) fn route(&self, tail: usize, head: usize) -> [(f64, f64); 4] {
+        let start = if tail < head {
+            self.x(self.extents[tail].label_end)
+        } else {
+            self.x(self.extents[tail].label)
+        };
+        let lane = self.lane(head);
+        [
+            (start, self.y(tail)),
+            (lane, self.y(tail)),
+            (lane, self.y(head)),
+            (self.x(self.extents[head].label), self.y(head)),
+        ]
+    }
+}
+
+pub(crate) fn drawn(layout: &Layout, options: &SvgOptions) -> String {
+    let diagram = &options.diagram;
+    let geometry = Geometry::new(
+        layout,
+        diagram.gap,
+        diagram.font_size,
+        options.advance,
+        diagram.line_height,
+        diagram.margin,
+    );
+    let (width, height) = (geometry.width(), geometry.height());
+    let font_size = diagram.font_size;
+    let mut svg = String::new();
+    writeln!(
+        svg,
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{width}\" height=\"{height}\" \
+         viewBox=\"0 0 {width} {height}\" font-family=\"{}\" font-size=\"{font_size}\">",
+        escaped(&options.font_family),
+    )
+    .unwrap();
+    writeln!(svg, "<style>{STYLE}</style>").unwrap();
+    writeln!(
+        svg,
+        "<defs><marker id=\"diagv-arrow\" viewBox=\"0 0 10 10\" refX=\"10\" refY=\"5\" \
+         markerWidth=\"6\" markerHeight=\"6\" orient=\"auto-start-reverse\">\
+         <path d=\"M0 0L10 5L0 10z\"/></marker></defs>"
+    )
+    .unwrap();
+
+    svg.push_str("<g class=\"edges\">\n");
+    for edge in layout.edges.iter() {
+        let (tail, head) = (&layout.nodes[edge.tail], &layout.nodes[edge.head]);
+        let (t, h) = (tail.id.index(), head.id.index());
+        let back = if edge.head < edge.tail { " back" } else { "" };
+        write!(
+            svg,
+            "<g class=\"edge edge-{t}-{h} from-{t} to-{h}{back}\"><title>{} → {}</title>",
+            escaped(&tail.label),
+            escaped(&head.label),
+        )
+        .unwrap();
+        if edge.tail == edge.head {
+            let x = geometry.x(geometry.extents[edge.tail].label_end) - geometry.char_width / 2.0;
+            let y = geometry.y(edge.tail);
+            let r = num(geometry.char_width / 2.0);
+            writeln!(
+                svg,
+                "<path class=\"line\" d=\"M{} {}A{r} {r} 0 1 1 {} {}\" \
+                 marker-end=\"url(#diagv-arrow)\"/></g>",
+                num(x - r),
+                y,
+                num(x + r),
+                y,
+            )
+            .unwrap();
+        } else {
+            let points: Vec<String> = geometry
+                .route(edge.tail, edge.head)
+                .iter()
+                .map(|(x, y)| format!("{x},{y}"))
+                .collect();
+            writeln!(
+                svg,
+                "<polyline class=\"line\" points=\"{}\" marker-end=\"url(#diagv-arrow)\"/></g>",
+                points.join(" "),
+            )
+            .unwrap();
+        }
+    }
+    svg.push_str("</g>\n");
+
+    svg.push_str("<g class=\"junctions\">\n");
+    let radius = num(font_size / 7.0);
+    for junction in layout.junctions.iter() {
+        let classes: Vec<String> = junction
+            .edges
+            .iter()
+            .map(|e| {
+                let edge = &layout.edges[*e];
+                let t = layout.nodes[edge.tail].id.index();
+                let h = layout.nodes[edge.head].id.index();
+                format!("edge-{t}-{h} from-{t} to-{h}")
+            })
+            .collect();
+        writeln!(
+            svg,
+            "<circle class=\"junction {}\" cx=\"{}\" cy=\"{}\" r=\"{radius}\"/>",
+            classes.join(" "),
+            geometry.lane(junction.col),
+            geometry.y(junction.row),
+        )
+        .unwrap();
+    }
+    svg.push_str("</g>\n");
+
+    svg.push_str("<g class=\"nodes\">\n");
+    for (pos, node) in layout.nodes.iter().enumerate() {
+        let label = escaped(&node.label);
+        writeln!(
+            svg,
+            "<g class=\"node node-{}\"><title>{label}</title>\
+             <text x=\"{}\" y=\"{}\" textLength=\"{}\" lengthAdjust=\"spacingAndGlyphs\">{label}</text></g>",
+            node.id.index(),
+            geometry.x(geometry.extents[pos].label),
+            geometry.y(pos),
+            num(node.width as f64 * geometry.char_width),
+        )
+        .unwrap();
+    }
+    svg.push_str("</g>\n</svg>\n");
+    svg
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::generators;
+    use crate::{Graph, SvgOptions};
+
+    #[test]
+    fn svg_has_one_path_per_edge() {
+        let graph = generators::cyclic();
+        let svg = graph.svg(&graph.nodes(), &SvgOptions::default()).unwrap();
+        assert_eq!(svg.matches("<polyline").count(), 9);
+        assert_eq!(svg.matches(" back\"").count(), 4);
+        assert_eq!(svg.matches("<text").count(), 9);
+        assert!(svg.contains("<title>6 → 0</title>"));
+    }
+
+    #[test]
+    fn svg_is_deterministic_and_escaped() {
+        let graph: Graph<&str> = vec![("<a>", "b&c"), ("b&c", "b&c")].into_iter().collect();
+        let svg = graph.svg(&graph.nodes(), &SvgOptions::default()).unwrap();
+        assert_eq!(
+            svg,
+            graph.svg(&graph.nodes(), &SvgOptions::default()).unwrap()
+        );
+        assert!(svg.contains("<title>&lt;a&gt; → b&amp;c</title>"));
+        assert!(!svg.contains("<a>"));
+    }
+}
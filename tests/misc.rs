@@ -1,4 +1,4 @@
-use diagv::generators;
+use diagv::{generators, ColorMode, Glyphs, Graph, LineEnding, RenderOptions};
 
 static DIAGV_TEXT: &str = "\
 d-----+
@@ -43,10 +43,25 @@ fn test_format_works_for_cyclic() {
     assert_eq!(actual, expected);
 }
 #[test]
-#[should_panic(expected = "Omitting self loop")]
-fn test_format_not_implemented_for_cycle_1() {
+fn test_format_works_for_cycle_1() {
     let graph = generators::cycle(1);
-    graph.ascii().unwrap();
+    let actual = graph.ascii().unwrap();
+    assert_eq!(actual, "0@");
+}
+
+static SELF_LOOPS_TEXT: &str = "\
++-a@-+
+|    +-b-+
++--------+-c@\
+";
+
+#[test]
+fn test_format_marks_self_loops() {
+    let graph: Graph<char> = vec![('a', 'a'), ('a', 'b'), ('b', 'c'), ('c', 'c'), ('c', 'a')]
+        .into_iter()
+        .collect();
+    let actual = graph.ascii().unwrap();
+    assert_eq!(actual, SELF_LOOPS_TEXT);
 }
 #[test]
 fn test_format_works_for_sonic_3() {
@@ -62,3 +77,360 @@ fn test_format_works_for_sonic_3() {
     let expected = SONIC_3_TEXT;
     assert_eq!(actual, expected);
 }
+
+#[test]
+fn test_format_rejects_incomplete_order() {
+    let graph = generators::diagv();
+    assert!(graph
+        .ascii_with_order(&vec![&'d', &'i', &'a', &'g'])
+        .is_err());
+    assert!(graph
+        .ascii_with_order(&vec![&'d', &'i', &'a', &'g', &'g'])
+        .is_err());
+}
+
+static DIAGV_CUSTOM_TEXT: &str = "\
+d********#\r
+      i**#*****#\r
+         #**a**:*****#\r
+               #**g**#\r
+                     #**v\
+";
+
+#[test]
+fn test_render_with_custom_options() {
+    let graph = generators::diagv();
+    let options = RenderOptions {
+        glyphs: Glyphs {
+            padding: ' ',
+            vertical: ':',
+            horizontal: '*',
+            crossing: ':',
+            junctions: ['#'; 16],
+            self_loop: '@',
+            arrow: '>',
+            dashed_vertical: ':',
+            dashed_horizontal: '.',
+            dashed_crossing: ':',
+        },
+        gap: 2,
+        line_ending: LineEnding::CrLf,
+        ..Default::default()
+    };
+    let actual = graph.render(&graph.nodes(), &options).unwrap();
+    assert_eq!(actual, DIAGV_CUSTOM_TEXT);
+}
+
+#[test]
+fn test_render_truncates_long_labels() {
+    let graph = generators::sonic(3);
+    let options = RenderOptions {
+        max_label_width: 1,
+        truncation_marker: String::from("~"),
+        ..Default::default()
+    };
+    let actual = graph.render(&graph.nodes(), &options).unwrap();
+    assert_eq!(actual.lines().next().unwrap(), "+-~-+---+");
+}
+
+static CYCLIC_UNICODE_TEXT: &str = "\
+╭─0─┬───────────╮
+│   ╰─1         │
+│       ╭─2─────╂───────╮
+│       │     3─┤       │
+│       │       ├─4─────╂───────╮
+│       │       ╰─────5 │       │
+╰───────╂───────────────┴─6     │
+        │                   ╭─7 │
+        ╰───────────────────┴───┴─8\
+";
+
+#[test]
+fn test_render_with_unicode_glyphs() {
+    let graph = generators::cyclic();
+    let options = RenderOptions {
+        glyphs: Glyphs::UNICODE,
+        ..Default::default()
+    };
+    let actual = graph.render(&graph.nodes(), &options).unwrap();
+    assert_eq!(actual, CYCLIC_UNICODE_TEXT);
+}
+
+#[test]
+fn test_format_aligns_wide_labels() {
+    let graph: Graph<&str> = vec![("日本", "é"), ("é", "🦀")].into_iter().collect();
+    let actual = graph.ascii().unwrap();
+    let expected = "日本-+\n     +-é-+\n         +-🦀";
+    assert_eq!(actual, expected);
+}
+
+#[test]
+fn test_render_truncates_between_graphemes() {
+    let graph: Graph<&str> = vec![("日本語", "e\u{301}e\u{301}e\u{301}")]
+        .into_iter()
+        .collect();
+    let options = RenderOptions {
+        max_label_width: 4,
+        truncation_marker: String::from("…"),
+        ..Default::default()
+    };
+    let actual = graph.render(&graph.nodes(), &options).unwrap();
+    let expected = "日…-+\n    +-e\u{301}e\u{301}e\u{301}";
+    assert_eq!(actual, expected);
+}
+
+static CYCLIC_DIRECTED_TEXT: &str = "\
++>0-+-----------+
+:   +>1         |
+:       +>2-----|-------+
+:       :     3-+       |
+:       :       +>4-----|-------+
+:       :       +.....5 |       |
++.......:...............+>6     |
+        :                   +>7 |
+        +...................+...+>8\
+";
+
+#[test]
+fn test_render_with_arrows_and_dashed_back_edges() {
+    let graph = generators::cyclic();
+    let options = RenderOptions {
+        arrows: true,
+        dashed_back_edges: true,
+        ..Default::default()
+    };
+    let actual = graph.render(&graph.nodes(), &options).unwrap();
+    assert_eq!(actual, CYCLIC_DIRECTED_TEXT);
+}
+
+static DIAGV_COLOR_TEXT: &str = "\
+d\x1b[1m-----+\x1b[0m
+    i\x1b[1m-+\x1b[0m\x1b[2m---+\x1b[0m
+      \x1b[1m+-\x1b[0ma\x1b[1m-\x1b[0m\x1b[2m|\x1b[0m\x1b[1m---+\x1b[0m
+          \x1b[2m+-\x1b[0mg\x1b[1m-+\x1b[0m
+              \x1b[1m+-\x1b[0mv\
+";
+
+#[test]
+fn test_render_colors_lanes() {
+    let graph = generators::diagv();
+    let options = RenderOptions {
+        color: ColorMode::Always,
+        palette: vec![String::from("1"), String::from("2")],
+        ..Default::default()
+    };
+    let actual = graph.render(&graph.nodes(), &options).unwrap();
+    assert_eq!(actual, DIAGV_COLOR_TEXT);
+}
+
+#[test]
+fn test_to_dot_round_trips() {
+    let mut graph: Graph<String> = vec![("b", "a"), ("a", "say \"hi\""), ("a", "a")]
+        .into_iter()
+        .map(|(t, h)| (t.to_string(), h.to_string()))
+        .collect();
+    graph.add_node(String::from("isolated → ü"));
+    let dot = graph.to_dot();
+    assert_eq!(
+        dot,
+        "digraph {\n  \"b\";\n  \"a\";\n  \"say \\\"hi\\\"\";\n  \"isolated → ü\";\n  \
+         \"b\" -> \"a\";\n  \"a\" -> \"a\";\n  \"a\" -> \"say \\\"hi\\\"\";\n}\n"
+    );
+    let parsed = Graph::parse_dot(&dot).unwrap();
+    assert_eq!(parsed.nodes(), graph.nodes());
+    assert_eq!(
+        parsed.edges().collect::<Vec<_>>(),
+        graph.edges().collect::<Vec<_>>()
+    );
+}
+
+#[test]
+fn test_parse_edge_list() {
+    let graph =
+        Graph::parse_edge_list("# From a pipeline\nd\ta\ni a\n\ni  g\na v\ng v\nlonely\n").unwrap();
+    assert_eq!(
+        graph.ascii().unwrap(),
+        "d-----+\n    i-+---+\n      +-a-|------------+\n          +-g----------+\n                lonely |\n                       +-v"
+    );
+    let error = Graph::parse_edge_list("a b c\n").err().unwrap();
+    assert_eq!(error.to_string(), "Expected one or two nodes on line 1");
+}
+
+#[test]
+fn test_parse_adjacency_list() {
+    let graph = Graph::parse_adjacency_list("d: a\ni: a g\n# Comment\na: v\ng: v\nv:\n").unwrap();
+    assert_eq!(graph.ascii().unwrap(), DIAGV_TEXT);
+}
+
+#[test]
+fn test_parse_tgf() {
+    let tgf = "1 First node\n2\n3 Third\n# Comment\n#\n1 2 Edge label\n2 3\n";
+    let graph = Graph::parse_tgf(tgf).unwrap();
+    assert_eq!(
+        graph.ascii().unwrap(),
+        "First node-+\n           +-2-+\n               +-Third"
+    );
+    let (first, second) = (
+        graph.node_ids().next().unwrap(),
+        graph.node_ids().nth(1).unwrap(),
+    );
+    assert_eq!(graph.node_attrs(first)["label"], "First node");
+    assert_eq!(
+        graph.edge_attrs(first, second).unwrap()["label"],
+        "Edge label"
+    );
+    let error = Graph::parse_tgf("1\n#\n1 4\n").err().unwrap();
+    assert_eq!(error.to_string(), "Undeclared node \"4\" on line 3");
+}
+
+#[test]
+fn test_parse_dot_accepts_every_id_form() {
+    let graph =
+        Graph::parse_dot("digraph {\n  a -> 1 -> \"a\";\n  \"b\" + \"c\" -> <<i>d</i>>;\n}")
+            .unwrap();
+    assert_eq!(graph.nodes(), vec!["a", "1", "bc", "<i>d</i>"]);
+    assert_eq!(graph.edges().count(), 3);
+}
+
+#[test]
+fn test_parse_dot_expands_subgraphs() {
+    let graph = Graph::parse_dot(
+        "digraph {\n  subgraph cluster_app {\n    ui -> api;\n    subgraph cluster_data { db }\n  }\n  \
+         {ui api} -> log;\n  api -> {db cache};\n}",
+    )
+    .unwrap();
+    assert_eq!(graph.nodes(), vec!["ui", "db", "api", "log", "cache"]);
+    let edges: Vec<(&String, &String)> = graph
+        .edges()
+        .map(|(t, h)| (graph.node(t), graph.node(h)))
+        .collect();
+    assert_eq!(edges.len(), 5);
+    let subgraphs = graph.subgraphs();
+    assert_eq!(subgraphs.len(), 2);
+    assert_eq!(subgraphs[0].name, "cluster_app");
+    assert_eq!(subgraphs[1].parent, Some(0));
+    let members: Vec<&String> = subgraphs[0]
+        .nodes
+        .iter()
+        .map(|id| graph.node(*id))
+        .collect();
+    assert_eq!(members, vec!["ui", "api", "db"]);
+}
+
+#[test]
+fn test_to_dot_cannot_write_a_trailing_backslash() {
+    let mut graph = Graph::new();
+    graph.add_edge(String::from("C:\\dir"), String::from("C:\\"));
+    let dot = graph.to_dot();
+    assert!(dot.contains("  \"C:\\dir\";\n  \"C:\\\";\n"));
+    assert!(Graph::parse_dot(&dot).is_err());
+    graph.remove_node(&String::from("C:\\"));
+    let parsed = Graph::parse_dot(&graph.to_dot()).unwrap();
+    assert_eq!(parsed.nodes(), graph.nodes());
+}
+
+#[test]
+fn test_to_dot_keeps_subgraphs() {
+    let dot = "digraph {\n  \"a\";\n  \"b\";\n  \"c\";\n  subgraph \"outer\" {\n    \"a\";\n    \
+               \"b\";\n    subgraph \"inner\" {\n      \"b\";\n    }\n  }\n  \"a\" -> \"c\";\n}\n";
+    let graph = Graph::parse_dot(dot).unwrap();
+    assert_eq!(graph.subgraphs().len(), 2);
+    assert_eq!(graph.to_dot(), dot);
+}
+
+#[test]
+fn test_parse_dot_keeps_attributes() {
+    let graph = Graph::parse_dot(
+        "digraph {\n  rankdir=LR;\n  node [shape=box];\n  a [label=\"A\"];\n  \
+         subgraph cluster_x {\n    label=X;\n    node [color=red];\n    edge [style=dashed];\n    \
+         b -> c [weight=2];\n  }\n  c -> a -> d;\n  a [shape=oval];\n}",
+    )
+    .unwrap();
+    let id = |node: &str| graph.id_of(&node.to_string()).unwrap();
+    assert_eq!(graph.attrs()["rankdir"], "LR");
+    assert_eq!(graph.node_attrs(id("a"))["label"], "A");
+    assert_eq!(graph.node_attrs(id("a"))["shape"], "oval");
+    assert_eq!(graph.node_attrs(id("b"))["color"], "red");
+    assert_eq!(graph.node_attrs(id("d")).get("color"), None);
+    assert_eq!(graph.node_attrs(id("d"))["shape"], "box");
+    let attrs = graph.edge_attrs(id("b"), id("c")).unwrap();
+    assert_eq!((&attrs["style"][..], &attrs["weight"][..]), ("dashed", "2"));
+    assert!(graph.edge_attrs(id("c"), id("a")).unwrap().is_empty());
+    assert_eq!(graph.subgraphs()[0].attrs["label"], "X");
+    assert!(graph.ascii().unwrap().starts_with("+-A---"));
+    let dot = graph.to_dot();
+    assert!(dot.contains("  \"a\" [\"label\"=\"A\", \"shape\"=\"oval\"];\n"));
+    assert_eq!(Graph::parse_dot(&dot).unwrap().to_dot(), dot);
+}
+
+#[test]
+fn test_parse_dot_expands_escapes_in_node_labels() {
+    let graph = Graph::parse_dot(
+        "digraph G {\n  node [label=\"\\N\"];\n  a -> b;\n  b [label=\"two\\nlines\\l\"];\n  \
+         c [label=\"\\G: \\N \\\\\"];\n}",
+    )
+    .unwrap();
+    let labels: Vec<&str> = graph
+        .node_ids()
+        .map(|id| graph.node_attrs(id)["label"].as_str())
+        .collect();
+    assert_eq!(labels, vec!["a", "two lines", "G: c \\"]);
+    assert_eq!(
+        graph.ascii().unwrap(),
+        "a-+\n  +-two lines\n                G: c \\"
+    );
+}
+
+#[test]
+fn test_parse_dot_reads_undirected_graphs_ports_and_every_graph() {
+    let graphs = Graph::parse_dot_all(
+        "strict graph G {\n  a:out:s -- b:in [color=red];\n  b -- a;\n  a -- c;\n}\n\
+         digraph {\n  x -> y;\n}\n",
+    )
+    .unwrap();
+    assert_eq!(graphs.len(), 2);
+    let (name, graph) = &graphs[0];
+    assert_eq!(name.as_deref(), Some("G"));
+    assert!(!graph.is_directed());
+    assert_eq!(graph.edges().count(), 2);
+    let id = |node: &str| graph.id_of(&node.to_string()).unwrap();
+    let attrs = graph.edge_attrs(id("a"), id("b")).unwrap();
+    assert_eq!(attrs["tailport"], "out:s");
+    assert_eq!(attrs["headport"], "in");
+    assert_eq!(attrs["color"], "red");
+    assert_eq!(
+        graph.to_dot(),
+        "graph {\n  \"a\";\n  \"b\";\n  \"c\";\n  \"a\" -- \"b\" [\"color\"=\"red\", \
+         \"headport\"=\"in\", \"tailport\"=\"out:s\"];\n  \"a\" -- \"c\";\n}\n"
+    );
+    let graphml = Graph::parse_graphml(&graph.to_graphml()).unwrap();
+    assert!(!graphml.is_directed());
+    assert_eq!(graphml.to_dot(), graph.to_dot());
+    #[cfg(feature = "serde")]
+    {
+        let jgf = Graph::parse_jgf(&graph.to_jgf()).unwrap();
+        assert!(!jgf.is_directed());
+        assert_eq!(jgf.to_dot(), graph.to_dot());
+    }
+    let mermaid = Graph::parse_mermaid(&graph.to_mermaid()).unwrap();
+    assert_eq!(mermaid.edges().count(), 2);
+    assert!(mermaid
+        .edges()
+        .all(|(t, h)| mermaid.edge_attrs(t, h).unwrap()["arrowhead"] == "none"));
+    assert_eq!(graphs[1].0, None);
+    assert!(graphs[1].1.is_directed());
+    let error = Graph::parse_dot("digraph {}\ndigraph {}").err().unwrap();
+    assert_eq!(
+        error.to_string(),
+        "Expected the end but found 'digraph' on line 2"
+    );
+}
+
+#[test]
+fn test_parse_dot_errors_carry_a_span() {
+    let error = Graph::parse_dot("digraph {\n  a -> ;\n}").err().unwrap();
+    let span = error.span();
+    assert_eq!((span.line, span.column, span.width), (2, 8, 1));
+    assert_eq!(span.snippet, "  a -> ;");
+    assert!(error.diagnostic().ends_with("2 |   a -> ;\n  |        ^"));
+}